@@ -0,0 +1,58 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use tracing_stackdriver::Layer;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+fn bench_layer(criterion: &mut Criterion, name: &str, layer: Layer<Registry>) {
+    let subscriber = Registry::default().with(layer.with_writer(std::io::sink));
+    let mut group = criterion.benchmark_group(name);
+
+    tracing::subscriber::with_default(subscriber, || {
+        group.bench_function("plain", |bencher| {
+            bencher.iter(|| tracing::info!("some stackdriver message"))
+        });
+
+        group.bench_function("with_span", |bencher| {
+            let span = tracing::info_span!("stackdriver_span", foo = "bar", baz = 123);
+            let _guard = span.enter();
+
+            bencher.iter(|| tracing::info!("some stackdriver message"))
+        });
+
+        group.bench_function("with_http_request", |bencher| {
+            bencher.iter(|| {
+                tracing::info!(
+                    http_request.request_method = "GET",
+                    http_request.request_url = "/some/url/from/request",
+                    http_request.status = 200,
+                    http_request.latency = "0.23s",
+                    "some stackdriver message"
+                )
+            })
+        });
+
+        group.bench_function("with_labels", |bencher| {
+            bencher.iter(|| {
+                tracing::info!(
+                    labels.thread_count = 3,
+                    labels.is_production = true,
+                    labels.note = "A short note",
+                    "some stackdriver message"
+                )
+            })
+        });
+    });
+
+    group.finish();
+}
+
+fn formatting(criterion: &mut Criterion) {
+    bench_layer(criterion, "default", tracing_stackdriver::layer());
+    bench_layer(
+        criterion,
+        "minimal",
+        tracing_stackdriver::layer().with_minimal(),
+    );
+}
+
+criterion_group!(benches, formatting);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+//! Serves an `axum` app that writes a Stackdriver-compatible entry with the `httpRequest` of every
+//! request it handles, e.g. with `cargo run --example axum --features tower`
+use axum::{routing::get, Router};
+use std::net::SocketAddr;
+use tower_http::trace::TraceLayer;
+use tracing_stackdriver::{StackdriverMakeSpan, StackdriverOnResponse};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+fn router() -> Router {
+    Router::new().route("/", get(|| async { "Hello!" })).layer(
+        TraceLayer::new_for_http()
+            .make_span_with(StackdriverMakeSpan)
+            .on_response(StackdriverOnResponse),
+    )
+}
+
+#[tokio::main]
+async fn main() {
+    let subscriber = Registry::default().with(tracing_stackdriver::layer());
+    tracing::subscriber::set_global_default(subscriber).expect("Could not set up global logger");
+
+    let address = SocketAddr::from(([127, 0, 0, 1], 3000));
+    tracing::info!("listening on {address}");
+
+    // once the response to a request is sent, an entry is emitted with:
+    // {
+    //   "httpRequest": {
+    //     "latency": "0.0001s",
+    //     "protocol": "HTTP/1.1",
+    //     "requestMethod": "GET",
+    //     "requestUrl": "/",
+    //     "status": 200
+    //   },
+    //   "message": "finished processing request",
+    //   ...
+    // }
+    axum::Server::bind(&address)
+        .serve(router().into_make_service())
+        .await
+        .expect("Error serving app");
+}
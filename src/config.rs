@@ -0,0 +1,347 @@
+use inflector::Inflector;
+use std::sync::Arc;
+
+/// Handling of Events with an empty `target`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum EmptyTargetMode {
+    /// Keep the empty `target` field as-is
+    #[default]
+    Keep,
+    /// Omit the `target` field entirely
+    Omit,
+    /// Substitute the name of the crate that emitted the Event, derived from its module path
+    CrateName,
+}
+
+/// Casing applied to the keys of Event fields and labels
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum FieldCasing {
+    /// Convert keys to camelCase (e.g. `field_name` -> `fieldName`, or `field.name` -> `fieldName`)
+    #[default]
+    CamelCase,
+    /// Keep keys exactly as they were recorded
+    Preserve,
+}
+
+impl FieldCasing {
+    /// Applies the casing to a field key
+    pub(crate) fn apply(&self, key: &str) -> String {
+        match self {
+            Self::CamelCase => key.to_camel_case(),
+            Self::Preserve => key.to_owned(),
+        }
+    }
+
+    /// Applies the casing to the keys of nested objects within a value, including objects within
+    /// arrays
+    pub(crate) fn apply_deep(&self, value: serde_json::Value) -> serde_json::Value {
+        match (self, value) {
+            (Self::Preserve, value) => value,
+            (_, serde_json::Value::Object(fields)) => fields
+                .into_iter()
+                .map(|(key, value)| (self.apply(&key), self.apply_deep(value)))
+                .collect(),
+            (_, serde_json::Value::Array(values)) => values
+                .into_iter()
+                .map(|value| self.apply_deep(value))
+                .collect(),
+            (_, value) => value,
+        }
+    }
+}
+
+/// Stringification of boolean label values, as labels are always strings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoolLabelFormat {
+    /// Render booleans as `"true"` or `"false"`
+    #[default]
+    TrueFalse,
+    /// Render booleans as `"1"` or `"0"`
+    ZeroOne,
+}
+
+impl BoolLabelFormat {
+    /// Applies the format to a boolean label value, leaving other values as-is
+    pub(crate) fn apply(&self, value: serde_json::Value) -> serde_json::Value {
+        match (self, value) {
+            (Self::ZeroOne, serde_json::Value::Bool(value)) => {
+                serde_json::Value::from(if value { "1" } else { "0" })
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+/// Ordering of the fields recorded on each Event, which follow the special LogEntry fields
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldOrder {
+    /// Order fields alphabetically by key
+    #[default]
+    Alphabetical,
+    /// Order fields in the order they were recorded (e.g. the order of macro arguments), with
+    /// the fields of flattened spans first
+    InsertionOrder,
+}
+
+/// Formatting of the `remoteIp` and `serverIp` addresses of HTTP requests
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IpFormat {
+    /// Format addresses in their standard form, with IPv6 addresses compressed (e.g. `2001:db8::1`)
+    #[default]
+    Default,
+    /// Enclose IPv6 addresses in brackets (e.g. `[2001:db8::1]`), leaving IPv4 addresses as-is
+    BracketedV6,
+    /// Expand IPv6 addresses to all eight zero-padded groups (e.g.
+    /// `2001:0db8:0000:0000:0000:0000:0000:0001`), leaving IPv4 addresses as-is
+    ExpandedV6,
+}
+
+impl IpFormat {
+    /// Applies the format to a stringified IP address, leaving other values as-is
+    pub(crate) fn apply(&self, value: serde_json::Value) -> serde_json::Value {
+        let Some(Ok(std::net::IpAddr::V6(ip))) = value.as_str().map(str::parse) else {
+            return value;
+        };
+
+        match self {
+            Self::Default => value,
+            Self::BracketedV6 => serde_json::Value::from(format!("[{ip}]")),
+            Self::ExpandedV6 => serde_json::Value::from(
+                ip.segments()
+                    .map(|segment| format!("{segment:04x}"))
+                    .join(":"),
+            ),
+        }
+    }
+}
+
+/// Placement of `labels.`-prefixed fields in each entry
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LabelsMode {
+    /// Nest labels under the `logging.googleapis.com/labels` special field
+    #[default]
+    Google,
+    /// Promote labels to top-level payload fields (e.g. `labels.env` becomes `env`), which are
+    /// overridden by Event fields with the same key
+    RootFields,
+}
+
+/// Source of the `message` of each entry
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum MessageSource {
+    /// The `message` field that tracing records for formatted Event messages
+    #[default]
+    DefaultMessage,
+    /// A field with the given name (e.g. `msg`), which replaces any formatted message when present
+    Field(String),
+}
+
+/// Format of the entries written by a Layer
+#[cfg_attr(docsrs, doc(cfg(feature = "syslog")))]
+#[cfg(any(docsrs, feature = "syslog"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Cloud Logging JSON entries
+    #[default]
+    Json,
+    /// RFC5424 syslog lines, with entry fields as structured data
+    Syslog,
+}
+
+/// Formatting of floating-point Event fields
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// JSON numbers in their shortest form, which uses scientific notation for very small and
+    /// very large values (e.g. `1e-9`)
+    #[default]
+    Default,
+    /// Strings with a fixed number of decimal places (e.g. `"0.000000001"` for 9 places), since
+    /// JSON numbers can't be serialized with a fixed precision
+    FixedDecimal(usize),
+}
+
+impl FloatFormat {
+    /// Applies the format to the floats of a value, including those nested in objects and arrays
+    pub(crate) fn apply(&self, value: serde_json::Value) -> serde_json::Value {
+        let Self::FixedDecimal(precision) = self else {
+            return value;
+        };
+
+        match value {
+            serde_json::Value::Number(number) if number.is_f64() => match number.as_f64() {
+                Some(float) => serde_json::Value::from(format!("{float:.precision$}")),
+                None => serde_json::Value::Number(number),
+            },
+            serde_json::Value::Object(fields) => fields
+                .into_iter()
+                .map(|(key, value)| (key, self.apply(value)))
+                .collect(),
+            serde_json::Value::Array(values) => {
+                values.into_iter().map(|value| self.apply(value)).collect()
+            }
+            value => value,
+        }
+    }
+}
+
+/// Terminator written after each entry
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// A line feed (`\n`), as expected by the Cloud Logging agent
+    #[default]
+    Lf,
+    /// A carriage return and line feed (`\r\n`), for Windows consumers
+    CrLf,
+}
+
+impl LineEnding {
+    /// Returns the terminator as a string
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Platforms with different expectations for LogEntries, used as presets through
+/// `Layer::with_platform`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Platform {
+    /// Cloud Run services and jobs, where entries are collected from stdout and requests carry an
+    /// `X-Cloud-Trace-Context` header
+    CloudRun,
+    /// Google Kubernetes Engine, where entries are collected from stdout by the logging agent
+    Gke,
+    /// App Engine, where entries are collected from stdout and requests carry an
+    /// `X-Cloud-Trace-Context` header
+    AppEngine,
+    /// Anywhere else, where entries are written to the Cloud Logging API directly
+    #[default]
+    Generic,
+}
+
+impl Platform {
+    /// Returns the [monitored resource type](https://cloud.google.com/logging/docs/api/v2/resource-list)
+    /// of entries written from the platform
+    pub fn resource_type(&self) -> &'static str {
+        match self {
+            Self::CloudRun => "cloud_run_revision",
+            Self::Gke => "k8s_container",
+            Self::AppEngine => "gae_app",
+            Self::Generic => "global",
+        }
+    }
+
+    /// Returns whether or not requests to the platform carry an `X-Cloud-Trace-Context` header,
+    /// whose values are recorded as `trace_id` fields
+    pub fn uses_cloud_trace_context(&self) -> bool {
+        matches!(self, Self::CloudRun | Self::AppEngine)
+    }
+}
+
+/// Transformation of the nested key and value of a field routed by a custom field handler
+pub(crate) type FieldHandlerFn =
+    dyn Fn(&str, serde_json::Value) -> (String, serde_json::Value) + Send + Sync;
+
+/// Custom routing of Event fields with a given prefix into a nested object
+#[derive(Clone)]
+pub(crate) struct FieldHandler {
+    pub(crate) prefix: String,
+    pub(crate) key: String,
+    pub(crate) handler: Arc<FieldHandlerFn>,
+}
+
+impl FieldHandler {
+    /// Returns the nested key of a field (e.g. `requests` for `metric.requests`), if handled
+    pub(crate) fn nested_key<'a>(&self, key: &'a str) -> Option<&'a str> {
+        key.strip_prefix(self.prefix.as_str())?.strip_prefix('.')
+    }
+}
+
+/// Serialization of a typed span extension, if present in a span's extensions
+pub(crate) type ExtensionPromoterFn = dyn Fn(&tracing_subscriber::registry::Extensions<'_>) -> Option<serde_json::Value>
+    + Send
+    + Sync;
+
+/// Promotion of a typed span extension onto every Event within the span, under a given key
+#[derive(Clone)]
+pub(crate) struct ExtensionPromoter {
+    pub(crate) key: String,
+    pub(crate) promote: Arc<ExtensionPromoterFn>,
+}
+
+/// Reading of a label value from the context an Event is emitted in (e.g. a task-local)
+pub(crate) type DynamicLabelFn = dyn Fn() -> Option<String> + Send + Sync;
+
+/// A label whose value is read whenever an Event is formatted, under a given key
+#[derive(Clone)]
+pub(crate) struct DynamicLabel {
+    pub(crate) key: String,
+    pub(crate) value: Arc<DynamicLabelFn>,
+}
+
+/// Information about the thread that emitted an Event, included under a `thread` field
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThreadInfo {
+    /// Include the name of the thread, if it has one
+    pub name: bool,
+    /// Include the numeric ID of the thread
+    pub id: bool,
+}
+
+impl ThreadInfo {
+    /// Serializes the configured information about the current thread, if any
+    pub(crate) fn current(&self) -> Option<serde_json::Value> {
+        let thread = std::thread::current();
+        let mut fields = serde_json::Map::new();
+
+        if self.name {
+            if let Some(name) = thread.name() {
+                fields.insert("name".to_owned(), serde_json::Value::from(name));
+            }
+        }
+
+        if self.id {
+            // ThreadId::as_u64 is unstable, so the numeric ID is parsed from ThreadId's Debug output
+            let id = format!("{:?}", thread.id());
+            let id = id
+                .trim_start_matches("ThreadId(")
+                .trim_end_matches(')')
+                .parse::<u64>()
+                .map(serde_json::Value::from)
+                .unwrap_or_else(|_| serde_json::Value::from(id));
+
+            fields.insert("id".to_owned(), id);
+        }
+
+        (!fields.is_empty()).then_some(serde_json::Value::Object(fields))
+    }
+}
+
+/// Formatting of `LogDuration` fields recorded through `valuable`
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DurationFormat {
+    /// Fractional seconds as a number (e.g. `1.5`)
+    Seconds,
+    /// Fractional milliseconds as a number (e.g. `1500.0`)
+    Millis,
+    /// Fractional seconds as a string with an `s` suffix (e.g. `"1.5s"`), matching the JSON
+    /// encoding of `google.protobuf.Duration` used by Cloud Logging
+    #[default]
+    ProtoDuration,
+}
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+impl DurationFormat {
+    /// Applies the format to a duration
+    pub(crate) fn apply(&self, duration: std::time::Duration) -> serde_json::Value {
+        match self {
+            Self::Seconds => serde_json::Value::from(duration.as_secs_f64()),
+            Self::Millis => serde_json::Value::from(duration.as_secs_f64() * 1000.0),
+            Self::ProtoDuration => serde_json::Value::from(format!("{}s", duration.as_secs_f64())),
+        }
+    }
+}
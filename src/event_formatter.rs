@@ -1,17 +1,35 @@
 use crate::{
+    config::{
+        BoolLabelFormat, DynamicLabel, EmptyTargetMode, ExtensionPromoter, FieldCasing,
+        FieldHandler, FieldOrder, FloatFormat, IpFormat, LabelsMode, LineEnding, MessageSource,
+        ThreadInfo,
+    },
     google::LogSeverity,
-    serializers::{SerializableContext, SerializableSpan, SourceLocation},
-    visitor::Visitor,
-    writer::WriteAdaptor,
+    log_entry::{LogEntry, LogEntryResource, LogEntrySourceLocation},
+    serializers::{
+        is_special_span_field, span_fields, SerializableContext, SerializableSpan,
+        SerializableSpanFields,
+    },
+    visitor::{Visitor, REDACTED},
+    writer::{AsciiFormatter, SeverityColorFormatter, WriteAdaptor},
 };
-use serde::ser::{SerializeMap, Serializer as _};
-use std::fmt;
-use time::{format_description::well_known::Rfc3339, OffsetDateTime};
-use tracing_core::{Event, Subscriber};
+use serde::Serialize;
+use serde_json::ser::CompactFormatter;
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+use tracing_core::{Event, Metadata, Subscriber};
 use tracing_subscriber::{
-    field::VisitOutput,
     fmt::{
-        format::{self, JsonFields},
+        format::{self, FmtSpan, JsonFields},
         FmtContext, FormatEvent,
     },
     registry::LookupSpan,
@@ -25,8 +43,6 @@ enum Error {
     Serialization(#[from] serde_json::Error),
     #[error(transparent)]
     Io(#[from] std::io::Error),
-    #[error("Time formatting error: {0}")]
-    Time(#[from] time::error::Format),
 }
 
 impl From<Error> for fmt::Error {
@@ -35,97 +51,551 @@ impl From<Error> for fmt::Error {
     }
 }
 
+thread_local! {
+    // xorshift64 state for sampling, seeded per thread from std's randomized hasher keys
+    static SAMPLING_STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+}
+
+/// Returns true for a random fraction of calls, approximately equal to `rate`
+fn sample(rate: f64) -> bool {
+    SAMPLING_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+
+        // the top 53 bits fit an f64 mantissa exactly, giving a uniform value in [0, 1)
+        ((x >> 11) as f64 / (1u64 << 53) as f64) < rate
+    })
+}
+
 /// Tracing Event formatter for Stackdriver layers
+#[derive(Clone)]
 pub struct EventFormatter {
     pub(crate) include_source_location: bool,
+    pub(crate) source_location_min_severity: LogSeverity,
+    pub(crate) source_function_from_span: bool,
+    pub(crate) structured_payload: bool,
+    pub(crate) resource_type: Option<&'static str>,
+    pub(crate) receive_timestamp: bool,
+    pub(crate) project_id: Option<String>,
+    pub(crate) target_as_log_name: bool,
+    pub(crate) version_label: Option<String>,
+    pub(crate) dynamic_labels: Vec<DynamicLabel>,
+    pub(crate) labels_mode: LabelsMode,
+    pub(crate) bool_label_format: BoolLabelFormat,
+    pub(crate) include_target: bool,
+    pub(crate) empty_target: EmptyTargetMode,
+    pub(crate) field_casing: FieldCasing,
+    pub(crate) span_field_casing: FieldCasing,
+    pub(crate) deep_casing: bool,
+    pub(crate) float_format: FloatFormat,
+    pub(crate) ip_format: IpFormat,
+    pub(crate) max_field_string_len: Option<usize>,
+    pub(crate) field_order: FieldOrder,
+    pub(crate) ansi: bool,
+    pub(crate) thread_info: ThreadInfo,
+    pub(crate) ascii_escapes: bool,
+    pub(crate) line_ending: LineEnding,
+    pub(crate) error_counter: Option<Arc<AtomicU64>>,
+    pub(crate) default_severity: LogSeverity,
+    pub(crate) muted_severities: Vec<LogSeverity>,
+    pub(crate) span_events: FmtSpan,
+    pub(crate) span_summaries: bool,
+    pub(crate) span_event_severity: LogSeverity,
+    pub(crate) debug_sample_rate: f64,
+    pub(crate) numeric_severity: bool,
+    pub(crate) redacted_fields: Vec<String>,
+    pub(crate) json_string_fields: Vec<String>,
+    pub(crate) span_field: String,
+    pub(crate) parent_span_field: Option<String>,
+    pub(crate) event_name_field: Option<String>,
+    pub(crate) message_source: MessageSource,
+    pub(crate) request_id_field: Option<String>,
+    pub(crate) field_handlers: Vec<FieldHandler>,
+    pub(crate) extension_promoters: Vec<ExtensionPromoter>,
+    pub(crate) transforms: Vec<Arc<dyn crate::FieldTransform>>,
+    pub(crate) span_fields_by_name: bool,
+    pub(crate) flatten_span_fields: bool,
+    pub(crate) flatten_single_field_spans: bool,
+    pub(crate) include_span: bool,
+    pub(crate) span_start_times: bool,
+    pub(crate) span_field_prefix: Option<String>,
+    pub(crate) result_objects: bool,
+    pub(crate) grpc_labels: bool,
+    #[cfg(all(tracing_unstable, feature = "valuable"))]
+    pub(crate) duration_format: crate::DurationFormat,
+    #[cfg(all(tracing_unstable, feature = "valuable"))]
+    pub(crate) max_value_depth: Option<usize>,
+    #[cfg(feature = "regex")]
+    pub(crate) redaction_patterns: Vec<regex::Regex>,
+    pub(crate) trace_template: Option<String>,
+    pub(crate) cloud_trace_context: bool,
     #[cfg(feature = "opentelemetry")]
     pub(crate) cloud_trace_configuration: Option<crate::CloudTraceConfiguration>,
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) include_trace_state: bool,
+    #[cfg(feature = "opentelemetry")]
+    pub(crate) include_span_kind_and_status: bool,
+    #[cfg(feature = "syslog")]
+    pub(crate) output_format: crate::OutputFormat,
 }
 
 impl EventFormatter {
-    /// Internal event formatting for a given serializer
-    fn format_event<S>(
+    /// Checks a raw field name (or its nested key, e.g. `http_request.authorization`) against the
+    /// configured redactions and redaction patterns
+    pub(crate) fn is_redacted(&self, key: &str) -> bool {
+        let nested_key = key.split_once('.').map(|(_, nested_key)| nested_key);
+
+        #[cfg(feature = "regex")]
+        if self.redaction_patterns.iter().any(|pattern| {
+            pattern.is_match(key)
+                || nested_key.is_some_and(|nested_key| pattern.is_match(nested_key))
+        }) {
+            return true;
+        }
+
+        self.redacted_fields
+            .iter()
+            .any(|field| field == key || Some(field.as_str()) == nested_key)
+    }
+
+    /// Counts an entry that was dropped because it couldn't be formatted, if configured
+    fn count_error(&self) {
+        if let Some(error_counter) = &self.error_counter {
+            error_counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the level-derived severity of an Event, or the configured severity of span
+    /// lifecycle events (which are emitted with the metadata of their span)
+    pub(crate) fn severity_of(&self, meta: &Metadata) -> LogSeverity {
+        if meta.is_span() {
+            self.span_event_severity
+        } else {
+            LogSeverity::from(meta.level())
+        }
+    }
+
+    /// Checks whether or not an entry of a level-derived severity is dropped by debug sampling
+    fn is_sampled_out(&self, severity: LogSeverity) -> bool {
+        severity <= LogSeverity::Debug
+            && self.debug_sample_rate < 1.0
+            && !sample(self.debug_sample_rate)
+    }
+
+    /// Returns the fields that every entry starts with: its timestamps, the monitored resource of
+    /// the configured platform and the shape of its payload
+    fn entry_start(&self) -> LogEntry {
+        LogEntry {
+            time: Some(SystemTime::now()),
+            receive_timestamp: self.receive_timestamp.then(SystemTime::now),
+            // logging agents detect the monitored resource of entries on their own, keeping this
+            // one in the payload as a record of the platform that the entry was written from
+            resource: self.resource_type.map(|resource_type| LogEntryResource {
+                resource_type: resource_type.to_owned(),
+                labels: self
+                    .project_id
+                    .iter()
+                    .map(|project_id| ("project_id".to_owned(), project_id.clone()))
+                    .collect(),
+            }),
+            numeric_severity: self.numeric_severity,
+            json_payload: self.structured_payload,
+            ..LogEntry::default()
+        }
+    }
+
+    /// Serializes pre-extracted Event data (e.g. from tests or replayed events) into a LogEntry
+    /// JSON string without a Subscriber, handling fields exactly as `format_event` does. Span
+    /// information is the name and fields of the Event's span, if any. Entries that would be
+    /// dropped (i.e. sampled-out or muted entries) are formatted as empty strings.
+    pub(crate) fn format_fields(
+        &self,
+        severity: LogSeverity,
+        message: &str,
+        fields: serde_json::Map<String, serde_json::Value>,
+        span: Option<(&str, serde_json::Map<String, serde_json::Value>)>,
+    ) -> Result<String, fmt::Error> {
+        self.try_format_fields(severity, message, fields, span)
+            .map_err(fmt::Error::from)
+    }
+
+    fn try_format_fields(
+        &self,
+        severity: LogSeverity,
+        message: &str,
+        fields: serde_json::Map<String, serde_json::Value>,
+        span: Option<(&str, serde_json::Map<String, serde_json::Value>)>,
+    ) -> Result<String, Error> {
+        if self.is_sampled_out(severity) {
+            return Ok(String::new());
+        }
+
+        let mut span_object = None;
+        let mut inherited_fields = serde_json::Map::new();
+
+        if let Some((name, span_fields)) = span {
+            let mut object = serde_json::Map::new();
+
+            for (key, value) in span_fields {
+                let value = if self.is_redacted(&key) {
+                    serde_json::Value::from(REDACTED)
+                } else {
+                    value
+                };
+
+                if !is_special_span_field(&key) {
+                    object.insert(self.span_field_casing.apply(&key), value.clone());
+                }
+
+                inherited_fields.insert(key, value);
+            }
+
+            object.insert("name".to_owned(), serde_json::Value::from(name));
+            span_object = Some(serde_json::Value::Object(object));
+        }
+
+        let mut visitor = Visitor::new(severity, self.entry_start(), self);
+        visitor.inherit_span_fields(inherited_fields);
+
+        if let Some(span_object) = span_object {
+            visitor.insert(self.span_field.as_str(), span_object);
+        }
+
+        visitor.insert_field_value("message".to_owned(), serde_json::Value::from(message));
+
+        for (key, value) in fields {
+            visitor.insert_field_value(key, value);
+        }
+
+        if self.muted_severities.contains(&visitor.severity()) {
+            return Ok(String::new());
+        }
+
+        Ok(serde_json::to_string(&visitor.finish())?)
+    }
+
+    /// Internal event formatting for a given serializer, returning the severity of the entry
+    fn format_event<S, F>(
         &self,
         context: &FmtContext<S, JsonFields>,
-        mut serializer: serde_json::Serializer<WriteAdaptor>,
+        mut serializer: serde_json::Serializer<WriteAdaptor, F>,
         event: &Event,
-    ) -> Result<(), Error>
+    ) -> Result<LogSeverity, Error>
     where
         S: Subscriber + for<'span> LookupSpan<'span>,
+        F: serde_json::ser::Formatter,
     {
-        let time = OffsetDateTime::now_utc().format(&Rfc3339)?;
         let meta = event.metadata();
-        let severity = LogSeverity::from(meta.level());
+        let severity = self.severity_of(meta);
 
+        // the span of the Event, used consistently for span fields, labels and trace data: an
+        // explicit parent if one was provided, or the current span otherwise (including when an
+        // explicit parent can no longer be looked up, e.g. because it closed concurrently)
         let span = event
             .parent()
             .and_then(|id| context.span(id))
             .or_else(|| context.lookup_current());
 
-        // FIXME: derive an accurate entry count ahead of time
-        let mut map = serializer.serialize_map(None)?;
-
-        // serialize custom fields
-        map.serialize_entry("time", &time)?;
-        map.serialize_entry("target", &meta.target())?;
-
-        if self.include_source_location {
-            if let Some(file) = meta.file() {
-                map.serialize_entry(
-                    "logging.googleapis.com/sourceLocation",
-                    &SourceLocation {
-                        file,
-                        line: meta.line(),
-                    },
-                )?;
-            }
+        let mut entry = self.entry_start();
+
+        // non-reserved fields of the payload, derived from the Event's metadata and span
+        let mut payload = Vec::new();
+
+        let target = match (meta.target(), &self.empty_target) {
+            ("", EmptyTargetMode::Omit) => None,
+            ("", EmptyTargetMode::CrateName) => meta
+                .module_path()
+                .and_then(|module_path| module_path.split("::").next()),
+            (target, _) => Some(target),
+        };
+
+        if let (true, Some(project_id), Some(target)) =
+            (self.target_as_log_name, &self.project_id, target)
+        {
+            entry.log_name = Some(crate::google::format_log_name(project_id, target));
+        }
+
+        if let (true, Some(target)) = (self.include_target, target) {
+            payload.push(("target", serde_json::Value::from(target)));
+        }
+
+        if let Some(key) = &self.event_name_field {
+            payload.push((key.as_str(), serde_json::Value::from(meta.name())));
         }
 
+        if self.include_source_location && severity >= self.source_location_min_severity {
+            entry.source_location = meta.file().map(|file| LogEntrySourceLocation {
+                file: file.to_owned(),
+                line: meta.line(),
+                function: span
+                    .as_ref()
+                    .filter(|_| self.source_function_from_span)
+                    .map(|span| span.name().to_owned()),
+            });
+        }
+
+        // W3C tracestate of the parent context, derived from OpenTelemetry
+        #[cfg_attr(not(feature = "opentelemetry"), allow(unused_mut))]
+        let mut trace_state: Option<String> = None;
+
+        // kind and status of the OpenTelemetry span, included as labels when configured
+        #[cfg(feature = "opentelemetry")]
+        let mut span_kind_label: Option<&str> = None;
+        #[cfg(feature = "opentelemetry")]
+        let mut span_status_label: Option<&str> = None;
+
+        // the fields of a flattened span, handled like Event fields
+        let mut promoted_span_fields = Vec::new();
+
+        // whether or not the span object is included, which single-field spans are flattened into
+        // the Event in place of
+        let mut include_span = self.include_span;
+
         // serialize the current span and its leaves
-        if let Some(span) = span {
-            map.serialize_entry("span", &SerializableSpan::new(&span))?;
-            map.serialize_entry("spans", &SerializableContext::new(context))?;
+        if let Some(span) = &span {
+            if let Some(key) = &self.parent_span_field {
+                payload.push((key.as_str(), serde_json::Value::from(span.name())));
+            }
+
+            if self.flatten_span_fields || self.flatten_single_field_spans {
+                let fields: Vec<_> = span_fields(span, self)
+                    .into_iter()
+                    .filter(|(key, _)| !is_special_span_field(key))
+                    .collect();
+
+                let single_field = self.flatten_single_field_spans && fields.len() == 1;
+
+                if single_field {
+                    include_span = false;
+                }
+
+                if self.flatten_span_fields || single_field {
+                    promoted_span_fields = fields
+                        .into_iter()
+                        .map(|(key, value)| match &self.span_field_prefix {
+                            Some(prefix) => (format!("{prefix}.{key}"), value),
+                            None => (key, value),
+                        })
+                        .collect();
+                }
+            }
+
+            if include_span {
+                payload.push((
+                    self.span_field.as_str(),
+                    serde_json::to_value(SerializableSpan::new(span, self))?,
+                ));
+            }
+
+            payload.push((
+                "spans",
+                serde_json::to_value(SerializableContext::new(span, self))?,
+            ));
+
+            if self.span_fields_by_name {
+                payload.push((
+                    "spanFields",
+                    serde_json::to_value(SerializableSpanFields::new(span, self))?,
+                ));
+            }
 
             #[cfg(feature = "opentelemetry")]
-            if let (Some(crate::CloudTraceConfiguration { project_id }), Some(otel_data)) = (
+            if let (
+                Some(cloud_trace_configuration),
+                Some((span_id, builder_trace_id, parent_cx, span_kind, status)),
+            ) = (
                 self.cloud_trace_configuration.as_ref(),
-                span.extensions().get::<tracing_opentelemetry::OtelData>(),
+                // the nearest span with OpenTelemetry data, which may be an ancestor of the
+                // Event's span if that span wasn't recorded by the OpenTelemetry layer
+                span.scope().find_map(|span| {
+                    span.extensions()
+                        .get::<tracing_opentelemetry::OtelData>()
+                        .map(|otel_data| {
+                            (
+                                otel_data.builder.span_id,
+                                otel_data.builder.trace_id,
+                                otel_data.parent_cx.clone(),
+                                otel_data.builder.span_kind.clone(),
+                                otel_data.builder.status.clone(),
+                            )
+                        })
+                }),
             ) {
-                use opentelemetry::trace::TraceContextExt;
+                use opentelemetry::trace::{SpanKind, Status, TraceContextExt};
 
-                let builder = &otel_data.builder;
+                if self.include_span_kind_and_status {
+                    span_kind_label = span_kind.map(|span_kind| match span_kind {
+                        SpanKind::Client => "client",
+                        SpanKind::Server => "server",
+                        SpanKind::Producer => "producer",
+                        SpanKind::Consumer => "consumer",
+                        SpanKind::Internal => "internal",
+                    });
 
-                if let Some(span_id) = builder.span_id {
-                    map.serialize_entry("logging.googleapis.com/spanId", &span_id.to_string())?;
+                    span_status_label = match status {
+                        Status::Unset => None,
+                        Status::Ok => Some("ok"),
+                        Status::Error { .. } => Some("error"),
+                    };
                 }
 
-                let (trace_id, trace_sampled) = if otel_data.parent_cx.has_active_span() {
-                    let span_ref = otel_data.parent_cx.span();
+                entry.span_id = span_id.map(|span_id| span_id.to_string());
+
+                let (trace_id, trace_sampled) = if parent_cx.has_active_span() {
+                    let span_ref = parent_cx.span();
                     let span_context = span_ref.span_context();
 
+                    if self.include_trace_state {
+                        trace_state = Some(span_context.trace_state().header())
+                            .filter(|header| !header.is_empty());
+                    }
+
                     (Some(span_context.trace_id()), span_context.is_sampled())
                 } else {
-                    (builder.trace_id, false)
+                    (builder_trace_id, false)
                 };
 
-                if let Some(trace_id) = trace_id {
-                    map.serialize_entry(
-                        "logging.googleapis.com/trace",
-                        &format!("projects/{project_id}/traces/{trace_id}",),
-                    )?;
-                }
+                // a trace derived from OpenTelemetry takes precedence over `trace_id` fields
+                entry.trace = trace_id.map(|trace_id| {
+                    crate::google::format_trace(
+                        self.trace_template.as_deref(),
+                        self.project_id
+                            .as_deref()
+                            .unwrap_or(&cloud_trace_configuration.project_id),
+                        trace_id,
+                    )
+                });
 
-                if trace_sampled {
-                    map.serialize_entry("logging.googleapis.com/trace_sampled", &true)?;
-                }
+                entry.trace_sampled = trace_sampled;
             }
         }
 
         // serialize the stackdriver-specific fields with a visitor
-        let mut visitor = Visitor::new(severity, map);
+        let mut visitor = Visitor::new(severity, entry, self);
+        visitor.set_message_field(meta.fields());
+
+        if let Some(trace_state) = trace_state {
+            visitor.insert_label("tracestate", serde_json::Value::from(trace_state));
+        }
+
+        #[cfg(feature = "opentelemetry")]
+        for (key, value) in [
+            ("span.kind", span_kind_label),
+            ("span.status", span_status_label),
+        ] {
+            if let Some(value) = value {
+                visitor.insert_label(key, serde_json::Value::from(value));
+            }
+        }
+
+        // inherit special fields from the current span and its ancestors
+        if let Some(span) = &span {
+            for span in span.scope().from_root() {
+                visitor.inherit_span_fields(span_fields(&span, self));
+            }
+        }
+
+        for (key, value) in payload {
+            visitor.insert(key, value);
+        }
+
+        for (key, value) in promoted_span_fields {
+            visitor.insert_field_value(key, value);
+        }
+
+        if let Some(thread) = self.thread_info.current() {
+            visitor.insert("thread", thread);
+        }
+
+        // typed span extensions are promoted from the nearest span that has them
+        if let Some(span) = &span {
+            for promoter in &self.extension_promoters {
+                if let Some(value) = span
+                    .scope()
+                    .find_map(|span| (promoter.promote)(&span.extensions()))
+                {
+                    visitor.insert(promoter.key.as_str(), value);
+                }
+            }
+        }
+
         event.record(&mut visitor);
-        visitor.finish().map_err(Error::from)?;
-        Ok(())
+        let entry = visitor.finish();
+        entry.serialize(&mut serializer)?;
+        std::io::Write::flush(&mut serializer.into_inner())?;
+        Ok(entry.severity)
+    }
+
+    /// Writes a formatted entry (including its line ending), returning the severity it was emitted
+    /// with
+    fn write_entry<S>(
+        &self,
+        context: &FmtContext<S, JsonFields>,
+        mut writer: format::Writer,
+        event: &Event,
+    ) -> Result<LogSeverity, fmt::Error>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        #[cfg(feature = "syslog")]
+        if self.output_format == crate::OutputFormat::Syslog {
+            let mut entry = String::new();
+            let serializer = serde_json::Serializer::new(WriteAdaptor::new(&mut entry));
+            let result = self
+                .format_event(context, serializer, event)
+                .and_then(|severity| Ok((severity, serde_json::from_str(&entry)?)));
+
+            let (severity, entry) = match result {
+                Ok(entry) => entry,
+                Err(error) => {
+                    self.count_error();
+                    return Err(error.into());
+                }
+            };
+
+            writer.write_str(&crate::syslog::SyslogFormatter.format(entry))?;
+
+            writer.write_str(self.line_ending.as_str())?;
+
+            return Ok(severity);
+        }
+
+        let write_adaptor = WriteAdaptor::new(&mut writer);
+
+        let result = match (self.ascii_escapes, self.ansi) {
+            (false, false) => {
+                let serializer = serde_json::Serializer::new(write_adaptor);
+                self.format_event(context, serializer, event)
+            }
+            (true, false) => {
+                let serializer =
+                    serde_json::Serializer::with_formatter(write_adaptor, AsciiFormatter);
+                self.format_event(context, serializer, event)
+            }
+            (false, true) => {
+                let formatter = SeverityColorFormatter::new(CompactFormatter);
+                let serializer = serde_json::Serializer::with_formatter(write_adaptor, formatter);
+                self.format_event(context, serializer, event)
+            }
+            (true, true) => {
+                let formatter = SeverityColorFormatter::new(AsciiFormatter);
+                let serializer = serde_json::Serializer::with_formatter(write_adaptor, formatter);
+                self.format_event(context, serializer, event)
+            }
+        };
+
+        if result.is_err() {
+            self.count_error();
+        }
+
+        let severity = result?;
+
+        writer.write_str(self.line_ending.as_str())?;
+
+        Ok(severity)
     }
 }
 
@@ -142,9 +612,25 @@ where
     where
         S: Subscriber + for<'span> LookupSpan<'span>,
     {
-        let serializer = serde_json::Serializer::new(WriteAdaptor::new(&mut writer));
-        self.format_event(context, serializer, event)?;
-        writeln!(writer)
+        // sampled-out Events are dropped before any output (including the line ending) is written
+        if self.is_sampled_out(self.severity_of(event.metadata())) {
+            return Ok(());
+        }
+
+        // muted severities are only known once every field has been visited, so entries are
+        // buffered until then instead of being written directly
+        if self.muted_severities.is_empty() {
+            return self.write_entry(context, writer, event).map(|_| ());
+        }
+
+        let mut entry = String::new();
+        let severity = self.write_entry(context, format::Writer::new(&mut entry), event)?;
+
+        if self.muted_severities.contains(&severity) {
+            return Ok(());
+        }
+
+        writer.write_str(&entry)
     }
 }
 
@@ -152,8 +638,72 @@ impl Default for EventFormatter {
     fn default() -> Self {
         Self {
             include_source_location: true,
+            source_location_min_severity: LogSeverity::Default,
+            source_function_from_span: false,
+            structured_payload: false,
+            resource_type: None,
+            receive_timestamp: false,
+            project_id: None,
+            target_as_log_name: false,
+            version_label: None,
+            dynamic_labels: Vec::new(),
+            labels_mode: LabelsMode::default(),
+            bool_label_format: BoolLabelFormat::default(),
+            include_target: true,
+            empty_target: EmptyTargetMode::default(),
+            field_casing: FieldCasing::default(),
+            span_field_casing: FieldCasing::Preserve,
+            deep_casing: false,
+            float_format: FloatFormat::default(),
+            ip_format: IpFormat::default(),
+            max_field_string_len: None,
+            field_order: FieldOrder::default(),
+            ansi: false,
+            thread_info: ThreadInfo::default(),
+            ascii_escapes: false,
+            line_ending: LineEnding::default(),
+            error_counter: None,
+            default_severity: LogSeverity::Default,
+            muted_severities: Vec::new(),
+            span_events: FmtSpan::NONE,
+            span_summaries: false,
+            span_event_severity: LogSeverity::Debug,
+            debug_sample_rate: 1.0,
+            numeric_severity: false,
+            redacted_fields: Vec::new(),
+            json_string_fields: Vec::new(),
+            span_field: "span".to_owned(),
+            parent_span_field: None,
+            event_name_field: None,
+            message_source: MessageSource::default(),
+            request_id_field: None,
+            field_handlers: Vec::new(),
+            extension_promoters: Vec::new(),
+            transforms: Vec::new(),
+            span_fields_by_name: false,
+            flatten_span_fields: false,
+            flatten_single_field_spans: false,
+            include_span: true,
+            span_start_times: false,
+            span_field_prefix: None,
+            result_objects: false,
+            grpc_labels: false,
+            #[cfg(all(tracing_unstable, feature = "valuable"))]
+            duration_format: crate::DurationFormat::default(),
+            #[cfg(all(tracing_unstable, feature = "valuable"))]
+            max_value_depth: None,
+            #[cfg(feature = "regex")]
+            redaction_patterns: Vec::new(),
+            trace_template: None,
+            cloud_trace_context: false,
             #[cfg(feature = "opentelemetry")]
             cloud_trace_configuration: None,
+            #[cfg(feature = "opentelemetry")]
+            include_trace_state: false,
+            #[cfg(feature = "opentelemetry")]
+            include_span_kind_and_status: false,
+            #[cfg(feature = "syslog")]
+            output_format: crate::OutputFormat::default(),
         }
     }
 }
@@ -1,14 +1,15 @@
 use serde::Serialize;
 use std::{convert::Infallible, fmt, str::FromStr};
-use tracing_core::Level;
+use tracing_core::{Level, LevelFilter};
 
 /// The severity of the event described in a log entry, expressed as standard severity levels.
 /// [See Google's LogSeverity docs here](https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry#LogSeverity).
+/// Severities are ordered from least (`Default`) to most (`Emergency`) severe.
 #[cfg_attr(
     all(tracing_unstable, feature = "valuable"),
     derive(valuable::Valuable)
 )]
-#[derive(Debug, Default, Serialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum LogSeverity {
     /// Log entry has no assigned severity level
@@ -50,6 +51,76 @@ impl fmt::Display for LogSeverity {
     }
 }
 
+impl LogSeverity {
+    /// Converts a severity into the closest `LevelFilter`, for building severity-based filters
+    /// out of tracing's `Level`s. Severities without a direct `Level` equivalent are rounded down
+    /// to the next-most-verbose level (e.g. `Notice` becomes `INFO`), and `Default` enables all
+    /// levels.
+    pub fn to_level_filter(&self) -> LevelFilter {
+        match self {
+            Self::Default => LevelFilter::TRACE,
+            Self::Debug => LevelFilter::DEBUG,
+            Self::Info | Self::Notice => LevelFilter::INFO,
+            Self::Warning => LevelFilter::WARN,
+            Self::Error | Self::Critical | Self::Alert | Self::Emergency => LevelFilter::ERROR,
+        }
+    }
+
+    /// Returns the numeric code of the severity used by the Cloud Logging API (e.g. `200` for
+    /// `Info`), where more severe levels have higher codes
+    pub fn as_number(&self) -> u16 {
+        match self {
+            Self::Default => 0,
+            Self::Debug => 100,
+            Self::Info => 200,
+            Self::Notice => 300,
+            Self::Warning => 400,
+            Self::Error => 500,
+            Self::Critical => 600,
+            Self::Alert => 700,
+            Self::Emergency => 800,
+        }
+    }
+
+    /// ANSI SGR color code used when colorizing entries for terminal output
+    pub(crate) fn ansi_color(&self) -> Option<&'static str> {
+        match self {
+            Self::Default => None,
+            Self::Debug => Some("34"),
+            Self::Info => Some("32"),
+            Self::Notice => Some("36"),
+            Self::Warning => Some("33"),
+            Self::Error => Some("31"),
+            Self::Critical | Self::Alert | Self::Emergency => Some("1;31"),
+        }
+    }
+
+    /// Returns the equivalent syslog severity, with `Default` treated as informational
+    #[cfg(feature = "syslog")]
+    pub(crate) fn as_syslog_severity(&self) -> u8 {
+        match self {
+            Self::Emergency => 0,
+            Self::Alert => 1,
+            Self::Critical => 2,
+            Self::Error => 3,
+            Self::Warning => 4,
+            Self::Notice => 5,
+            Self::Default | Self::Info => 6,
+            Self::Debug => 7,
+        }
+    }
+}
+
+/// Converts a `Level` into its equivalent severity, which can be recorded as a `severity` field to
+/// override the severity of an Event:
+///
+/// ```rust
+/// use tracing::Level;
+/// use tracing_stackdriver::LogSeverity;
+///
+/// let severity = LogSeverity::from(&Level::WARN);
+/// tracing::info!(severity = %severity, "emitted as a WARNING");
+/// ```
 impl From<&Level> for LogSeverity {
     fn from(level: &Level) -> Self {
         match level {
@@ -61,11 +132,31 @@ impl From<&Level> for LogSeverity {
     }
 }
 
+impl From<Level> for LogSeverity {
+    fn from(level: Level) -> Self {
+        Self::from(&level)
+    }
+}
+
+/// Converts a `LevelFilter` into the severity of its most verbose level. `LevelFilter::OFF` has
+/// no `Level` equivalent, and is treated as the most restrictive severity.
+impl From<LevelFilter> for LogSeverity {
+    fn from(level_filter: LevelFilter) -> Self {
+        match level_filter.into_level() {
+            Some(level) => Self::from(&level),
+            None => Self::Emergency,
+        }
+    }
+}
+
+/// Parses severities case-insensitively, accepting both the `Display` (e.g. `NOTICE`) and `Debug`
+/// (e.g. `Notice`) forms of every severity, as well as tracing's `Level` names
 impl FromStr for LogSeverity {
     type Err = Infallible;
 
     fn from_str(string: &str) -> Result<Self, Self::Err> {
         let severity = match string.to_lowercase().as_str() {
+            "default" => Self::Default,
             "debug" | "trace" => Self::Debug,
             "info" => Self::Info,
             "notice" => Self::Notice,
@@ -147,6 +238,53 @@ impl HttpRequest {
     }
 }
 
+/// Headers whose values are always redacted by `header_labels`, since they carry credentials
+#[cfg(any(docsrs, feature = "http"))]
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// Collects an allowlisted set of headers (matched case-insensitively) into a map of labels,
+/// which can be recorded as a `labels` field (e.g. `tracing::info!(labels = ?labels, ...)`).
+/// Multiple values of the same header are joined with `, `, and headers that aren't valid UTF-8
+/// are skipped. Sensitive headers like `authorization` and `cookie` are redacted, even when
+/// allowlisted.
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[cfg(any(docsrs, feature = "http"))]
+pub fn header_labels(
+    headers: &http::HeaderMap,
+    allowlist: &[&str],
+) -> std::collections::BTreeMap<String, String> {
+    let mut labels = std::collections::BTreeMap::new();
+
+    for name in allowlist {
+        let Ok(name) = http::header::HeaderName::from_bytes(name.as_bytes()) else {
+            continue;
+        };
+
+        let values: Option<Vec<_>> = headers
+            .get_all(&name)
+            .iter()
+            .map(|value| value.to_str().ok())
+            .collect();
+
+        let value = match values {
+            Some(values) if values.is_empty() => continue,
+            Some(_) if SENSITIVE_HEADERS.contains(&name.as_str()) => "[REDACTED]".to_owned(),
+            Some(values) => values.join(", "),
+            None => continue,
+        };
+
+        labels.insert(name.as_str().to_owned(), value);
+    }
+
+    labels
+}
+
 #[cfg(all(tracing_unstable, feature = "valuable"))]
 static HTTP_REQUEST_FIELDS: &[valuable::NamedField<'static>] = &[
     valuable::NamedField::new("requestMethod"),
@@ -231,6 +369,222 @@ impl valuable::Structable for HttpRequest {
     }
 }
 
+/// A `Duration` that can be recorded as an Event field through `valuable`, formatted according to
+/// the `DurationFormat` of the Layer (e.g. `tracing::info!(elapsed = LogDuration(elapsed).as_value())`)
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogDuration(pub std::time::Duration);
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+static LOG_DURATION_NAME: &str = "LogDuration";
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+static LOG_DURATION_FIELDS: &[valuable::NamedField<'static>] = &[
+    valuable::NamedField::new("secs"),
+    valuable::NamedField::new("nanos"),
+];
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+impl LogDuration {
+    /// Recovers a recorded `LogDuration` from its structured representation
+    pub(crate) fn from_structable(structable: &dyn valuable::Structable) -> Option<Self> {
+        #[derive(Default)]
+        struct DurationVisitor {
+            secs: Option<u64>,
+            nanos: Option<u32>,
+        }
+
+        impl valuable::Visit for DurationVisitor {
+            fn visit_value(&mut self, _: valuable::Value<'_>) {}
+
+            fn visit_named_fields(&mut self, named_values: &valuable::NamedValues<'_>) {
+                self.secs = named_values
+                    .get_by_name("secs")
+                    .and_then(valuable::Value::as_u64);
+                self.nanos = named_values
+                    .get_by_name("nanos")
+                    .and_then(valuable::Value::as_u32);
+            }
+        }
+
+        if structable.definition().name() != LOG_DURATION_NAME {
+            return None;
+        }
+
+        let mut visitor = DurationVisitor::default();
+        structable.visit(&mut visitor);
+
+        Some(Self(std::time::Duration::new(
+            visitor.secs?,
+            visitor.nanos?,
+        )))
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+impl valuable::Valuable for LogDuration {
+    fn as_value(&self) -> valuable::Value<'_> {
+        valuable::Value::Structable(self)
+    }
+
+    fn visit(&self, visit: &mut dyn valuable::Visit) {
+        visit.visit_named_fields(&valuable::NamedValues::new(
+            LOG_DURATION_FIELDS,
+            &[
+                valuable::Value::U64(self.0.as_secs()),
+                valuable::Value::U32(self.0.subsec_nanos()),
+            ],
+        ));
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+impl valuable::Structable for LogDuration {
+    fn definition(&self) -> valuable::StructDef<'_> {
+        valuable::StructDef::new_static(
+            LOG_DURATION_NAME,
+            valuable::Fields::Named(LOG_DURATION_FIELDS),
+        )
+    }
+}
+
+/// A `SystemTime` that can be recorded as an Event field through `valuable`, formatted as an RFC3339
+/// timestamp (e.g. `tracing::info!(expires_at = LogTimestamp(expires_at).as_value())`). `Instant`s
+/// have no wall-clock time, so they should be converted to a `SystemTime` (e.g.
+/// `SystemTime::now() - instant.elapsed()`) before being recorded (see [`LogInstant`]).
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogTimestamp(pub std::time::SystemTime);
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+static LOG_TIMESTAMP_NAME: &str = "LogTimestamp";
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+static LOG_TIMESTAMP_FIELDS: &[valuable::NamedField<'static>] =
+    &[valuable::NamedField::new("unix_timestamp_nanos")];
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+impl LogTimestamp {
+    /// Recovers a recorded `LogTimestamp` from its structured representation
+    pub(crate) fn from_structable(structable: &dyn valuable::Structable) -> Option<Self> {
+        #[derive(Default)]
+        struct TimestampVisitor {
+            unix_timestamp_nanos: Option<i128>,
+        }
+
+        impl valuable::Visit for TimestampVisitor {
+            fn visit_value(&mut self, _: valuable::Value<'_>) {}
+
+            fn visit_named_fields(&mut self, named_values: &valuable::NamedValues<'_>) {
+                self.unix_timestamp_nanos = named_values
+                    .get_by_name("unix_timestamp_nanos")
+                    .and_then(valuable::Value::as_i128);
+            }
+        }
+
+        if structable.definition().name() != LOG_TIMESTAMP_NAME {
+            return None;
+        }
+
+        let mut visitor = TimestampVisitor::default();
+        structable.visit(&mut visitor);
+
+        crate::timestamp::from_unix_timestamp_nanos(visitor.unix_timestamp_nanos?).map(Self)
+    }
+
+    /// Formats the timestamp as an RFC3339 string
+    pub(crate) fn format(&self) -> String {
+        crate::timestamp::format(self.0)
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+impl valuable::Valuable for LogTimestamp {
+    fn as_value(&self) -> valuable::Value<'_> {
+        valuable::Value::Structable(self)
+    }
+
+    fn visit(&self, visit: &mut dyn valuable::Visit) {
+        let unix_timestamp_nanos = crate::timestamp::unix_timestamp_nanos(self.0);
+
+        visit.visit_named_fields(&valuable::NamedValues::new(
+            LOG_TIMESTAMP_FIELDS,
+            &[valuable::Value::I128(unix_timestamp_nanos)],
+        ));
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+impl valuable::Structable for LogTimestamp {
+    fn definition(&self) -> valuable::StructDef<'_> {
+        valuable::StructDef::new_static(
+            LOG_TIMESTAMP_NAME,
+            valuable::Fields::Named(LOG_TIMESTAMP_FIELDS),
+        )
+    }
+}
+
+/// An `Instant` recorded as an Event field through `valuable` (e.g.
+/// `tracing::info!(deadline = LogInstant(deadline).as_value())`). `Instant`s have no wall-clock
+/// time and can't be represented as timestamps, so they're emitted as `null`, with a warning
+/// written to stderr the first time one is recorded. Convert them to a `SystemTime` and record a
+/// [`LogTimestamp`] instead.
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogInstant(pub std::time::Instant);
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+static LOG_INSTANT_NAME: &str = "LogInstant";
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+static LOG_INSTANT_WARNING: std::sync::Once = std::sync::Once::new();
+
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+impl LogInstant {
+    /// Checks whether or not a recorded value is a `LogInstant`, warning once per process when it
+    /// is, since its value is dropped
+    pub(crate) fn is_structable(structable: &dyn valuable::Structable) -> bool {
+        let is_instant = structable.definition().name() == LOG_INSTANT_NAME;
+
+        if is_instant {
+            LOG_INSTANT_WARNING.call_once(|| {
+                eprintln!(
+                    "[tracing-stackdriver] Instant fields have no wall-clock time, and are emitted as null"
+                )
+            });
+        }
+
+        is_instant
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+impl valuable::Valuable for LogInstant {
+    fn as_value(&self) -> valuable::Value<'_> {
+        valuable::Value::Structable(self)
+    }
+
+    fn visit(&self, visit: &mut dyn valuable::Visit) {
+        visit.visit_named_fields(&valuable::NamedValues::new(&[], &[]));
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+#[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+impl valuable::Structable for LogInstant {
+    fn definition(&self) -> valuable::StructDef<'_> {
+        valuable::StructDef::new_static(LOG_INSTANT_NAME, valuable::Fields::Named(&[]))
+    }
+}
+
 /// Configuration for projects looking to use the [Cloud Trace](https://cloud.google.com/trace) integration
 /// through [trace-specific fields](https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry#FIELDS.trace) in
 /// a LogEntry.
@@ -243,3 +597,117 @@ pub struct CloudTraceConfiguration {
     /// prefixing and identifying collectecd traces.
     pub project_id: String,
 }
+
+/// Default template for the `logging.googleapis.com/trace` field
+const DEFAULT_TRACE_TEMPLATE: &str = "projects/{project_id}/traces/{trace_id}";
+
+/// Formats a trace ID for the `logging.googleapis.com/trace` field, using the default template
+/// when no custom template is provided
+pub(crate) fn format_trace(
+    trace_template: Option<&str>,
+    project_id: &str,
+    trace_id: impl fmt::Display,
+) -> String {
+    trace_template
+        .unwrap_or(DEFAULT_TRACE_TEMPLATE)
+        .replace("{project_id}", project_id)
+        .replace("{trace_id}", &trace_id.to_string())
+}
+
+/// Splits an [`X-Cloud-Trace-Context`](https://cloud.google.com/trace/docs/trace-context#legacy-http-header)
+/// header value (`TRACE_ID/SPAN_ID;o=OPTIONS`) into its trace ID, its span ID (converted from
+/// decimal to the hexadecimal format of LogEntry span IDs) and whether or not it was sampled.
+/// Plain trace IDs are returned as-is.
+pub(crate) fn parse_cloud_trace_context(value: &str) -> (&str, Option<String>, bool) {
+    let (ids, options) = value.split_once(";o=").unwrap_or((value, ""));
+    let sampled = options == "1";
+
+    match ids.split_once('/') {
+        Some((trace_id, span_id)) => (
+            trace_id,
+            span_id
+                .parse::<u64>()
+                .ok()
+                .map(|span_id| format!("{span_id:016x}")),
+            sampled,
+        ),
+        None => (ids, None, sampled),
+    }
+}
+
+/// Checks that a custom trace template includes both the `{project_id}` and `{trace_id}`
+/// placeholders
+pub(crate) fn validate_trace_template(
+    trace_template: String,
+) -> Result<String, InvalidTraceTemplate> {
+    for placeholder in ["{project_id}", "{trace_id}"] {
+        if !trace_template.contains(placeholder) {
+            return Err(InvalidTraceTemplate {
+                template: trace_template,
+                placeholder,
+            });
+        }
+    }
+
+    Ok(trace_template)
+}
+
+/// Maximum length of the `[LOG_ID]` segment of a log name
+const MAX_LOG_ID_LENGTH: usize = 512;
+
+/// Formats a target (e.g. `my_crate::module`) as a log name following Google's rules for log
+/// IDs, with path separators replaced by periods (e.g. `my_crate.module`), slashes URL-encoded and
+/// any other characters that log IDs can't contain replaced by underscores
+pub(crate) fn format_log_name(project_id: &str, target: &str) -> String {
+    let mut log_id = String::with_capacity(target.len());
+
+    for character in target.replace("::", ".").chars() {
+        let mut buffer = [0; 4];
+        let segment = match character {
+            '/' => "%2F",
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' | '.' => {
+                character.encode_utf8(&mut buffer)
+            }
+            _ => "_",
+        };
+
+        // log IDs are truncated without splitting URL-encoded slashes
+        if log_id.len() + segment.len() > MAX_LOG_ID_LENGTH {
+            break;
+        }
+
+        log_id.push_str(segment);
+    }
+
+    format!("projects/{project_id}/logs/{log_id}")
+}
+
+/// Error returned when a custom trace template is missing a required placeholder
+#[derive(Debug, thiserror::Error)]
+#[error("Trace template {template:?} is missing a {placeholder} placeholder")]
+pub struct InvalidTraceTemplate {
+    template: String,
+    placeholder: &'static str,
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
+#[cfg(any(docsrs, feature = "opentelemetry"))]
+impl CloudTraceConfiguration {
+    /// Generate a configuration from the project ID reported by the [GCP metadata
+    /// server](https://cloud.google.com/compute/docs/metadata/overview). This is a blocking call
+    /// that times out quickly when not running on GCP. The metadata server host can be overridden
+    /// with the `GCE_METADATA_HOST` environment variable, where hostnames (unlike IP addresses) are
+    /// resolved through the system resolver before the timeout applies.
+    pub fn from_metadata_server() -> std::io::Result<Self> {
+        let project_id = crate::metadata::project_id()?;
+
+        Ok(Self::new(project_id))
+    }
+
+    /// Generate a configuration for the provided project ID
+    pub fn new(project_id: impl Into<String>) -> Self {
+        Self {
+            project_id: project_id.into(),
+        }
+    }
+}
@@ -1,8 +1,19 @@
-use crate::event_formatter::EventFormatter;
-use std::{fmt, io, ops::Deref};
+use crate::{
+    config::{DynamicLabel, ExtensionPromoter, FieldHandler},
+    event_formatter::EventFormatter,
+    serializers::SpanStartTime,
+};
+use std::{
+    fmt, io,
+    ops::Deref,
+    sync::{atomic::AtomicU64, Arc},
+};
 use tracing_core::{Event, Subscriber};
 use tracing_subscriber::{
-    fmt::{format::JsonFields, MakeWriter},
+    fmt::{
+        format::{FmtSpan, JsonFields},
+        MakeWriter,
+    },
     registry::LookupSpan,
 };
 
@@ -14,8 +25,6 @@ enum Error {
     Serialization(#[from] serde_json::Error),
     #[error(transparent)]
     Io(#[from] std::io::Error),
-    #[error("Time formatting error: {0}")]
-    Time(#[from] time::error::Format),
 }
 
 impl From<Error> for fmt::Error {
@@ -24,21 +33,51 @@ impl From<Error> for fmt::Error {
     }
 }
 
+/// The `fmt::Layer` wrapped by a Stackdriver Layer, writing to stdout by default
+type InnerLayer<S, W = fn() -> io::Stdout> =
+    tracing_subscriber::fmt::Layer<S, JsonFields, EventFormatter, W>;
+
+/// Configuration of the inner Layer applied through `map_inner`
+type InnerConfiguration<S> = Arc<dyn Fn(InnerLayer<S>) -> InnerLayer<S> + Send + Sync>;
+
 /// Create a configurable stackdriver-specific Layer and event formatter
 pub fn layer<S>() -> Layer<S>
 where
     S: Subscriber + for<'span> LookupSpan<'span>,
 {
     Layer(
-        tracing_subscriber::fmt::layer()
-            .json()
-            .event_format(EventFormatter::default()),
+        inner_layer(&EventFormatter::default(), &[]),
+        EventFormatter::default(),
+        Vec::new(),
     )
 }
 
+/// Builds an inner Layer from the `map_inner` configuration of a Layer, followed by its event
+/// formatter, which takes precedence over any event formatter set through `map_inner`
+fn inner_layer<S>(
+    event_formatter: &EventFormatter,
+    configurations: &[InnerConfiguration<S>],
+) -> InnerLayer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .json()
+        .event_format(event_formatter.clone());
+
+    configurations
+        .iter()
+        .fold(layer, |layer, configure| configure(layer))
+        .event_format(event_formatter.clone())
+}
+
 /// A tracing-compatible Layer implementation for Stackdriver
 pub struct Layer<S, W = fn() -> io::Stdout>(
-    tracing_subscriber::fmt::Layer<S, JsonFields, EventFormatter, W>,
+    InnerLayer<S, W>,
+    // copy of the inner Layer's event formatter, kept for re-building the inner Layer on clone
+    EventFormatter,
+    // configuration of the inner Layer through `map_inner`, replayed when re-building it on clone
+    Vec<InnerConfiguration<S>>,
 )
 where
     S: Subscriber + for<'span> LookupSpan<'span>;
@@ -53,25 +92,897 @@ where
     where
         M: for<'writer> MakeWriter<'writer> + 'static,
     {
-        Layer(self.0.with_writer(make_writer))
+        Layer(self.0.with_writer(make_writer), self.1, self.2)
+    }
+
+    /// Replaces the writer of the Layer with an in-memory ring buffer of parsed entries, returning
+    /// a reader that can wait for entries as they're written (e.g. by background tasks) instead of
+    /// racing on a shared buffer in tests
+    #[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+    #[cfg(feature = "testing")]
+    pub fn with_writer_make_for_test(
+        self,
+    ) -> (
+        Layer<S, crate::testing::TestWriter>,
+        crate::testing::TestReader,
+    ) {
+        let (writer, reader) = crate::testing::channel();
+
+        (self.with_writer(writer), reader)
+    }
+
+    /// Routes entries to different writers by severity, with each writer receiving the entries
+    /// at or above its severity (and below that of the next-most-severe route). Entries below
+    /// every route are written to the `default` writer.
+    pub fn with_severity_routing<M, D>(
+        self,
+        routes: Vec<(crate::LogSeverity, M)>,
+        default: D,
+    ) -> Layer<S, crate::SeverityRouting<M, D>>
+    where
+        M: for<'writer> MakeWriter<'writer> + 'static,
+        D: for<'writer> MakeWriter<'writer> + 'static,
+    {
+        self.with_writer(crate::SeverityRouting::new(routes, default))
+    }
+
+    /// Counts entries that are dropped because they couldn't be formatted or written, e.g. for
+    /// alerting on logging failures. Write failures are counted for the writer configured so far,
+    /// so this should be called after `with_writer` or `with_severity_routing`.
+    pub fn with_error_counter(self, counter: Arc<AtomicU64>) -> Layer<S, crate::ErrorCounting<W>> {
+        let Self(layer, event_formatter, configurations) =
+            self.map_event_format(|mut event_formatter| {
+                event_formatter.error_counter = Some(counter.clone());
+                event_formatter
+            });
+
+        Layer(
+            layer.map_writer(|make_writer| crate::ErrorCounting::new(make_writer, counter)),
+            event_formatter,
+            configurations,
+        )
+    }
+
+    /// Applies an arbitrary configuration to the inner `fmt::Layer` (e.g. `log_internal_errors`)
+    /// for settings without a method of their own on this Layer. Configurations are kept and
+    /// replayed when the Layer is cloned. The writer and event formatter are configured through
+    /// this Layer's methods instead, and take precedence over any set by `f`.
+    pub fn map_inner(
+        self,
+        f: impl Fn(InnerLayer<S>) -> InnerLayer<S> + Send + Sync + 'static,
+    ) -> Self {
+        let Self(layer, event_formatter, mut configurations) = self;
+        configurations.push(Arc::new(f));
+
+        // the inner Layer is re-built with the new configuration, keeping only its writer
+        let mut make_writer = None;
+
+        let _ = layer.map_writer(|inner_make_writer| {
+            make_writer = Some(inner_make_writer);
+            io::sink
+        });
+
+        let make_writer = make_writer.expect("map_writer maps writers immediately");
+
+        Self(
+            inner_layer(&event_formatter, &configurations).with_writer(make_writer),
+            event_formatter,
+            configurations,
+        )
+    }
+
+    /// Applies a configuration change to the event formatter of the Layer
+    fn map_event_format(self, f: impl FnOnce(EventFormatter) -> EventFormatter) -> Self {
+        let event_formatter = f(self.1);
+
+        Self(
+            self.0.event_format(event_formatter.clone()),
+            event_formatter,
+            self.2,
+        )
+    }
+
+    /// Emits an entry for a span lifecycle event, if enabled, as an Event within that span
+    fn on_span_event(
+        &self,
+        kind: FmtSpan,
+        message: &'static str,
+        id: &tracing_core::span::Id,
+        context: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        if self.1.span_events.clone() & kind.clone() == kind {
+            self.emit_span_entry(id, context, message, None);
+        }
+    }
+
+    /// Emits a summary entry for a closing span, if enabled, with the time elapsed since the span
+    /// was created
+    fn on_span_summary(
+        &self,
+        id: &tracing_core::span::Id,
+        context: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        if !self.1.span_summaries {
+            return;
+        }
+
+        let Some(span) = context.span(id) else {
+            return;
+        };
+
+        let message = format!("{} closed", span.name());
+        let elapsed = span
+            .extensions()
+            .get::<SpanStartTime>()
+            .and_then(|SpanStartTime(started_at)| started_at.elapsed().ok())
+            .map(|elapsed| format!("{}s", elapsed.as_secs_f64()));
+
+        drop(span);
+
+        self.emit_span_entry(id, context, &message, elapsed.as_deref());
+    }
+
+    /// Emits an entry as an Event within a span, using the metadata of that span
+    fn emit_span_entry(
+        &self,
+        id: &tracing_core::span::Id,
+        context: tracing_subscriber::layer::Context<'_, S>,
+        message: &str,
+        elapsed: Option<&str>,
+    ) {
+        if let Some(metadata) = context.metadata(id) {
+            let fields =
+                tracing_core::field::FieldSet::new(&["message", "elapsed"], metadata.callsite());
+            let message_field = fields.field("message").expect("message field is declared");
+            let elapsed_field = fields.field("elapsed").expect("elapsed field is declared");
+            let values = [
+                (
+                    &message_field,
+                    Some(&message as &dyn tracing_core::field::Value),
+                ),
+                (
+                    &elapsed_field,
+                    elapsed
+                        .as_ref()
+                        .map(|elapsed| elapsed as &dyn tracing_core::field::Value),
+                ),
+            ];
+            let values = fields.value_set(&values);
+
+            tracing_subscriber::layer::Layer::on_event(
+                self,
+                &Event::new_child_of(id.clone(), metadata, &values),
+                context,
+            );
+        }
+    }
+
+    /// Returns whether or not Events will include source locations
+    pub fn source_location_enabled(&self) -> bool {
+        self.1.include_source_location
+    }
+
+    /// Returns whether or not non-reserved Event fields are nested under a `jsonPayload` key
+    pub fn structured_payload_enabled(&self) -> bool {
+        self.1.structured_payload
+    }
+
+    /// Returns the casing applied to the keys of Event fields and labels
+    pub fn field_casing(&self) -> &crate::FieldCasing {
+        &self.1.field_casing
+    }
+
+    /// Returns the severity used for Events whose severity can't otherwise be determined
+    pub fn default_severity(&self) -> crate::LogSeverity {
+        self.1.default_severity
+    }
+
+    /// Serializes pre-extracted Event data into a LogEntry JSON string using the Layer's
+    /// configuration, without a Subscriber or `tracing` Event. Fields are keyed by their recorded
+    /// names (e.g. `labels.foo` or `http_request.request_method`), and `span` is the name and
+    /// fields of the Event's span, if any.
+    pub fn format_fields(
+        &self,
+        severity: crate::LogSeverity,
+        message: &str,
+        fields: serde_json::Map<String, serde_json::Value>,
+        span: Option<(&str, serde_json::Map<String, serde_json::Value>)>,
+    ) -> Result<String, fmt::Error> {
+        self.1.format_fields(severity, message, fields, span)
+    }
+
+    /// Configures a preset for the platform that entries are written from, which sets the
+    /// monitored resource type included in the `resource` of each entry, and whether or not
+    /// `trace_id` fields are parsed as `X-Cloud-Trace-Context` header values (as with
+    /// `with_cloud_trace_context`) for platforms that forward that header, i.e. `CloudRun` and
+    /// `AppEngine`. Entries from the `Generic` platform are assumed to be written to the Cloud
+    /// Logging API directly, and enable `with_structured_payload` as well.
+    pub fn with_platform(self, platform: crate::Platform) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.resource_type = Some(platform.resource_type());
+            event_formatter.cloud_trace_context = platform.uses_cloud_trace_context();
+
+            if platform == crate::Platform::Generic {
+                event_formatter.structured_payload = true;
+            }
+
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not `trace_id` fields are parsed as
+    /// [`X-Cloud-Trace-Context`](https://cloud.google.com/trace/docs/trace-context#legacy-http-header)
+    /// header values (`TRACE_ID/SPAN_ID;o=OPTIONS`), whose span ID and sampling decision are
+    /// written as `logging.googleapis.com/spanId` and `logging.googleapis.com/trace_sampled`
+    /// fields alongside the trace. Plain trace IDs are formatted as-is.
+    pub fn with_cloud_trace_context(self, cloud_trace_context: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.cloud_trace_context = cloud_trace_context;
+            event_formatter
+        })
+    }
+
+    /// Configures the ID of the project that entries are written from, which is included in the
+    /// monitored resource of entries written with `with_platform`, and used for
+    /// formatting `trace_id` fields of Events and their spans as `logging.googleapis.com/trace`
+    /// fields (e.g. `projects/{project_id}/traces/{trace_id}`) without OpenTelemetry. Takes
+    /// precedence over the project ID of a `CloudTraceConfiguration`.
+    pub fn with_project_id(self, project_id: impl Into<String>) -> Self {
+        let project_id = project_id.into();
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.project_id = Some(project_id);
+            event_formatter
+        })
+    }
+
+    /// Configures the template used for formatting `logging.googleapis.com/trace` fields, for
+    /// proxies that expect a different format than the default of
+    /// `projects/{project_id}/traces/{trace_id}`. Templates must include both the `{project_id}`
+    /// and `{trace_id}` placeholders.
+    pub fn with_trace_template(
+        self,
+        trace_template: impl Into<String>,
+    ) -> Result<Self, crate::InvalidTraceTemplate> {
+        let trace_template = crate::google::validate_trace_template(trace_template.into())?;
+
+        Ok(self.map_event_format(|mut event_formatter| {
+            event_formatter.trace_template = Some(trace_template);
+            event_formatter
+        }))
+    }
+
+    /// Configures whether or not the target of each Event is used as the log that its entry is
+    /// written to, formatting a `logName` field (e.g. `projects/{project_id}/logs/my_crate.module`)
+    /// so that different modules are routed to different logs. Requires a project ID configured
+    /// through `with_project_id`, and is omitted for Events without a target.
+    pub fn with_target_as_log_name(self, target_as_log_name: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.target_as_log_name = target_as_log_name;
+            event_formatter
+        })
+    }
+
+    /// Configures where `labels.`-prefixed fields are placed in each entry, defaulting to the
+    /// `logging.googleapis.com/labels` special field. Labels promoted to top-level fields with
+    /// `LabelsMode::RootFields` aren't subject to Cloud Logging's label limits.
+    pub fn with_labels_mode(self, labels_mode: crate::LabelsMode) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.labels_mode = labels_mode;
+            event_formatter
+        })
+    }
+
+    /// Configures how boolean label values (e.g. `labels.enabled = false`) are stringified,
+    /// defaulting to `"true"` or `"false"`
+    pub fn with_bool_label_format(self, bool_label_format: crate::BoolLabelFormat) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.bool_label_format = bool_label_format;
+            event_formatter
+        })
+    }
+
+    /// Configures a `version` label included in every entry (e.g. `env!("CARGO_PKG_VERSION")`) for
+    /// correlating entries with releases. Labels of the same key on spans or Events take precedence.
+    pub fn with_version_label(self, version: &str) -> Self {
+        let version = version.to_owned();
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.version_label = Some(version);
+            event_formatter
+        })
+    }
+
+    /// Adds a label whose value is read whenever an Event is formatted, omitting it when `value`
+    /// returns `None`. Values are read on the thread that emitted the Event, so they can come from
+    /// thread-locals or task-locals (e.g. `|| SESSION_ID.try_with(Clone::clone).ok()` for a tokio
+    /// task-local) without threading them through every span. Labels of the same key on spans or
+    /// Events take precedence.
+    pub fn with_dynamic_label<F>(self, key: &str, value: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        let dynamic_label = DynamicLabel {
+            key: key.to_owned(),
+            value: Arc::new(value),
+        };
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.dynamic_labels.push(dynamic_label);
+            event_formatter
+        })
+    }
+
+    /// Configures which span lifecycle events (e.g. `FmtSpan::CLOSE`) are emitted as entries, with
+    /// the severity configured through `with_span_event_severity`
+    pub fn with_span_events(self, kind: FmtSpan) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_events = kind;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not a summary entry is emitted whenever a span closes, with a
+    /// `"{name} closed"` message, the span's fields, and the time `elapsed` since it was created.
+    /// Summaries have the severity configured through `with_span_event_severity` (`DEBUG` by
+    /// default).
+    pub fn with_span_summaries(self, span_summaries: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_summaries = span_summaries;
+            event_formatter
+        })
+    }
+
+    /// Configures the severity of span lifecycle entries enabled through `with_span_events` and
+    /// `with_span_summaries`, defaulting to `DEBUG` so that they're kept out of `INFO`-level views
+    pub fn with_span_event_severity(self, severity: crate::LogSeverity) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_event_severity = severity;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not entries include a `receiveTimestamp` alongside `time`, for
+    /// pipelines where the logging agent doesn't set the time entries were received. Both are
+    /// taken when the entry is formatted, so they differ only slightly.
+    pub fn with_receive_timestamp(self, receive_timestamp: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.receive_timestamp = receive_timestamp;
+            event_formatter
+        })
     }
 
     /// Configures whether or not Events will include source locations in a special LogEntry field
     pub fn with_source_location(self, include_source_location: bool) -> Self {
-        Self(self.0.map_event_format(|mut event_formatter| {
+        self.map_event_format(|mut event_formatter| {
             event_formatter.include_source_location = include_source_location;
             event_formatter
-        }))
+        })
+    }
+
+    /// Configures the minimum severity of Events that include source locations (e.g. `WARNING`),
+    /// omitting them from routine entries to reduce their size. Only the severity of an Event's
+    /// level is considered, not any `severity` field.
+    pub fn with_source_location_min_severity(self, min_severity: crate::LogSeverity) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.source_location_min_severity = min_severity;
+            event_formatter
+        })
+    }
+
+    /// Configures the casing of Event field and label keys. `httpRequest` keys are always
+    /// camelCased, as expected by Cloud Logging.
+    pub fn with_field_casing(self, field_casing: crate::FieldCasing) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.field_casing = field_casing;
+            event_formatter
+        })
+    }
+
+    /// Configures the casing of span field keys under `span`, `spans` and `spanFields`, which
+    /// defaults to `FieldCasing::Preserve` (keeping keys exactly as they were recorded). Use
+    /// `FieldCasing::CamelCase` for consistency with Event fields.
+    pub fn with_span_field_casing(self, span_field_casing: crate::FieldCasing) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_field_casing = span_field_casing;
+            event_formatter
+        })
+    }
+
+    /// Configures the formatting of the `remoteIp` and `serverIp` addresses of HTTP requests,
+    /// whether recorded through `HttpRequest` or as `http_request.`-prefixed fields
+    pub fn with_ip_format(self, ip_format: crate::IpFormat) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.ip_format = ip_format;
+            event_formatter
+        })
+    }
+
+    /// Configures the formatting of floating-point Event fields (including floats nested within
+    /// objects and arrays), e.g. `FloatFormat::FixedDecimal(9)` to avoid the scientific notation
+    /// used for very small and very large values in metrics-like fields
+    pub fn with_float_format(self, float_format: crate::FloatFormat) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.float_format = float_format;
+            event_formatter
+        })
+    }
+
+    /// Configures a maximum length (in characters) for string fields, including messages and the
+    /// `Debug` output of fields like `?huge_struct`. Longer strings are truncated with an ellipsis,
+    /// and entries with truncated fields are marked with a `"__truncated": true` field.
+    pub fn with_max_field_string_len(self, max_len: usize) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.max_field_string_len = Some(max_len);
+            event_formatter
+        })
+    }
+
+    /// Configures the order of the fields recorded on each Event, defaulting to alphabetical
+    /// order. Fields follow the special LogEntry fields (e.g. `time` and `severity`) either way.
+    pub fn with_field_order(self, field_order: crate::FieldOrder) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.field_order = field_order;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not field casing is also applied to the keys of nested objects (e.g.
+    /// from `valuable` structures and lists), rather than to top-level field keys alone
+    pub fn with_deep_casing(self, deep_casing: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.deep_casing = deep_casing;
+            event_formatter
+        })
+    }
+
+    /// Configures a minimal formatting profile that skips per-Event work that isn't required for
+    /// a valid LogEntry: source locations are omitted, and fields are emitted with the exact keys
+    /// they were recorded with (e.g. `user_id` rather than `userId`).
+    pub fn with_minimal(self) -> Self {
+        self.with_source_location(false)
+            .with_field_casing(crate::FieldCasing::Preserve)
+    }
+
+    /// Configures whether or not the `function` of an Event's source location is populated from
+    /// the name of its enclosing span, which is the function name for `#[instrument]`-ed functions
+    pub fn with_source_function_from_span(self, source_function_from_span: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.source_function_from_span = source_function_from_span;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not non-reserved Event fields are nested under a `jsonPayload` key,
+    /// as expected when writing LogEntries to the Cloud Logging API directly. Special fields like
+    /// `severity`, `httpRequest` and `logging.googleapis.com/labels` remain at the top level.
+    pub fn with_structured_payload(self, structured_payload: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.structured_payload = structured_payload;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not the `severity` value of each entry is colorized with ANSI escape
+    /// codes, as a development-only mode for reading entries in a terminal.
+    ///
+    /// # Warning
+    ///
+    /// Colorized entries are **not valid JSON**: raw escape codes are written inside the `severity`
+    /// string, so logging agents and other JSON parsers reject them. Colors aren't suppressed for
+    /// writers that aren't terminals (e.g. files, pipes or log collectors), so this must never be
+    /// enabled in production. Gate it on a terminal instead, e.g. with
+    /// `with_ansi(std::io::stdout().is_terminal())` when writing to stdout.
+    pub fn with_ansi(self, ansi: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.ansi = ansi;
+            event_formatter
+        })
+    }
+
+    /// Configures the severity used for Events whose severity can't otherwise be determined (e.g.
+    /// from an unrecognized `severity` field), which are emitted as `DEFAULT` otherwise
+    pub fn with_default_severity(self, default_severity: crate::LogSeverity) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.default_severity = default_severity;
+            event_formatter
+        })
+    }
+
+    /// Configures the minimum severity of Events (e.g. `ERROR`) whose entries are flushed right
+    /// after they're written, reducing the chance of losing them on a crash when writing through
+    /// a buffered writer. Only the severity of an Event's level is considered, dropped entries
+    /// aren't flushed, and entries are never flushed explicitly by default. The writer configured
+    /// so far is the one flushed, so this should be called after `with_writer` or
+    /// `with_severity_routing`.
+    pub fn with_flush_on_severity(
+        self,
+        flush_severity: crate::LogSeverity,
+    ) -> Layer<S, crate::FlushOnSeverity<W>> {
+        let Self(layer, event_formatter, configurations) = self;
+
+        Layer(
+            layer
+                .map_writer(|make_writer| crate::FlushOnSeverity::new(make_writer, flush_severity)),
+            event_formatter,
+            configurations,
+        )
+    }
+
+    /// Configures severities whose entries are dropped entirely, matching the severity each entry
+    /// would be emitted with (including any `severity` field) exactly. Unlike a level filter, this
+    /// can drop e.g. `NOTICE` entries while keeping both `INFO` and `WARNING` entries.
+    pub fn with_muted_severities(self, muted_severities: &[crate::LogSeverity]) -> Self {
+        let muted_severities = muted_severities.to_vec();
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.muted_severities = muted_severities;
+            event_formatter
+        })
+    }
+
+    /// Configures the fraction (between `0.0` and `1.0`) of `DEBUG` and `TRACE` Events that are
+    /// kept, randomly dropping the rest to reduce the cost of high-volume debug logging. Unlike
+    /// level filtering, this keeps a representative sample of debug logs. More severe Events are
+    /// never dropped.
+    pub fn with_debug_sampling(self, rate: f64) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.debug_sample_rate = rate.clamp(0.0, 1.0);
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not entries include a `severityNumber` field with the numeric code
+    /// of their severity (e.g. `200` for `INFO`), in addition to the `severity` string
+    pub fn with_numeric_severity(self, numeric_severity: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.numeric_severity = numeric_severity;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not non-ASCII characters are escaped as `\uXXXX` sequences, for
+    /// legacy consumers that only accept ASCII output
+    pub fn with_ascii_escapes(self, ascii_escapes: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.ascii_escapes = ascii_escapes;
+            event_formatter
+        })
+    }
+
+    /// Configures the terminator written after each entry (a line feed by default)
+    pub fn with_line_ending(self, line_ending: crate::LineEnding) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.line_ending = line_ending;
+            event_formatter
+        })
+    }
+
+    /// Configures the information about the emitting thread (its name and/or ID) that's included
+    /// under a `thread` field of each entry
+    pub fn with_thread_info(self, thread_info: crate::ThreadInfo) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.thread_info = thread_info;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not entries include the `target` of their Event, which is still used
+    /// for log names configured through `with_target_as_log_name` when omitted
+    pub fn with_include_target(self, include_target: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.include_target = include_target;
+            event_formatter
+        })
+    }
+
+    /// Configures how Events with an empty `target` are formatted
+    pub fn with_empty_target(self, empty_target: crate::EmptyTargetMode) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.empty_target = empty_target;
+            event_formatter
+        })
+    }
+
+    /// Configures the key of the field that holds the span enclosing each Event, which defaults to
+    /// `span`
+    pub fn with_span_field_name(self, key: &str) -> Self {
+        let key = key.to_owned();
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_field = key;
+            event_formatter
+        })
+    }
+
+    /// Configures a top-level field that holds the name of each Event (e.g. as set with
+    /// `tracing::event!(name: "my_event", ...)`), or disables it with `None` (the default). Events
+    /// without an explicit name are named after their source location by tracing.
+    pub fn with_event_name_field(self, key: Option<&str>) -> Self {
+        let key = key.map(str::to_owned);
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.event_name_field = key;
+            event_formatter
+        })
+    }
+
+    /// Configures the field that entries take their `message` from, e.g.
+    /// `MessageSource::Field("msg".into())` for Events recorded as `info!(msg = "hello")`
+    pub fn with_message_source(self, message_source: crate::MessageSource) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.message_source = message_source;
+            event_formatter
+        })
+    }
+
+    /// Configures a top-level field that holds the name of the span enclosing each Event, if any
+    pub fn with_parent_span_field(self, key: &str) -> Self {
+        let key = key.to_owned();
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.parent_span_field = Some(key);
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not the fields of the current span are included on the Event itself
+    /// (with field casing applied), in addition to the nested span object unless it's disabled
+    /// through `with_span`. Fields recorded on the Event take precedence over flattened span
+    /// fields with the same key.
+    pub fn with_flattened_span_fields(self, flatten_span_fields: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.flatten_span_fields = flatten_span_fields;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not Events include the nested object of their current span. Span
+    /// labels, HTTP request fields and trace IDs are inherited either way.
+    pub fn with_span(self, include_span: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.include_span = include_span;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not serialized spans include the time they were created, as an
+    /// RFC3339 `startedAt` field (disabled by default)
+    pub fn with_span_start_times(self, span_start_times: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_start_times = span_start_times;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not the only field of a span with a single field is included on the
+    /// Event itself (with field casing applied), rather than under a nested span object. Fields
+    /// recorded on the Event take precedence over a flattened span field with the same key.
+    pub fn with_flatten_single_field_spans(self, flatten_single_field_spans: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.flatten_single_field_spans = flatten_single_field_spans;
+            event_formatter
+        })
+    }
+
+    /// Configures a prefix for span fields that are flattened into Events (e.g. `span` for
+    /// `span.foo`, which is camelCased to `spanFoo`), to avoid collisions with Event fields. Without
+    /// a prefix, Event fields take precedence over flattened span fields with the same key.
+    pub fn with_span_field_prefix(self, prefix: Option<&str>) -> Self {
+        let prefix = prefix.map(str::to_owned);
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_field_prefix = prefix;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not Events include a `spanFields` object that maps the name of each
+    /// enclosing span to its fields, preserving the fields of every level of nested spans. Spans
+    /// that share a name with one of their ancestors are keyed with an index suffix (e.g.
+    /// `handler_1`).
+    pub fn with_span_fields_by_name(self, span_fields_by_name: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.span_fields_by_name = span_fields_by_name;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not `Result` fields recorded with `Debug` (e.g. `result = ?result`)
+    /// are serialized as `{"ok": ...}` or `{"err": ...}` objects instead of strings. `Result`s are
+    /// detected from their `Debug` output, so other values that format as `Ok(..)` or `Err(..)`
+    /// are converted as well. Inner values are kept as JSON when their `Debug` output is valid
+    /// JSON (e.g. numbers and most strings), and as strings otherwise.
+    pub fn with_result_objects(self, result_objects: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.result_objects = result_objects;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not `grpc.method` and `grpc.status` fields are promoted to
+    /// `grpc_method` and `grpc_status` labels, for gRPC services without an `httpRequest`. Numeric
+    /// status codes are labeled with their canonical names (e.g. `5` as `NOT_FOUND`).
+    pub fn with_grpc_labels(self, grpc_labels: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.grpc_labels = grpc_labels;
+            event_formatter
+        })
+    }
+
+    /// Configures a field (e.g. `request_id`) whose value is copied into both a `request_id` label
+    /// and the `logging.googleapis.com/insertId` special field, when present on an Event. An
+    /// explicit `insert_id` field on the same Event takes precedence for the insert ID.
+    pub fn with_request_id_field(self, key: &str) -> Self {
+        let key = key.to_owned();
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.request_id_field = Some(key);
+            event_formatter
+        })
+    }
+
+    /// Registers a handler for Event fields with a given prefix (e.g. `metric` for `metric.requests`),
+    /// which nests those fields into an object under `key` instead of including them as-is. The
+    /// handler receives the nested key (e.g. `requests`) and value of each field, and returns
+    /// the key and value to nest. Custom handlers take precedence over built-in special fields
+    /// like `http_request` and `labels`, and are matched in the order they were registered.
+    pub fn with_field_handler(
+        self,
+        prefix: &str,
+        key: &str,
+        handler: impl Fn(&str, serde_json::Value) -> (String, serde_json::Value) + Send + Sync + 'static,
+    ) -> Self {
+        let field_handler = FieldHandler {
+            prefix: prefix.to_owned(),
+            key: key.to_owned(),
+            handler: Arc::new(handler),
+        };
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.field_handlers.push(field_handler);
+            event_formatter
+        })
+    }
+
+    /// Promotes a typed span extension (e.g. per-request context inserted with
+    /// `span.extensions_mut().insert(...)` by another Layer) onto every Event within the span,
+    /// serialized under `key`. The extension is taken from the nearest span that has one, and
+    /// Event fields with the same key take precedence.
+    pub fn with_extension_promoter<T>(self, key: &str) -> Self
+    where
+        T: serde::Serialize + Send + Sync + 'static,
+    {
+        let extension_promoter = ExtensionPromoter {
+            key: key.to_owned(),
+            promote: Arc::new(|extensions| {
+                extensions
+                    .get::<T>()
+                    .and_then(|extension| serde_json::to_value(extension).ok())
+            }),
+        };
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.extension_promoters.push(extension_promoter);
+            event_formatter
+        })
+    }
+
+    /// Configures a set of field names whose values are replaced with `"[REDACTED]"`, matched
+    /// against the original field name before camelCasing, whether recorded on Events or on their
+    /// spans. Nested fields (e.g. `http_request.authorization`) are matched by either their full
+    /// name or their nested key.
+    pub fn with_redacted_fields(self, fields: &[&str]) -> Self {
+        let redacted_fields = fields.iter().map(|field| field.to_string()).collect();
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.redacted_fields = redacted_fields;
+            event_formatter
+        })
+    }
+
+    /// Configures a set of string field names whose values are embedded as structured JSON when
+    /// they contain valid JSON (e.g. a pre-serialized `payload`), rather than as escaped strings.
+    /// Values that aren't valid JSON are kept as strings.
+    pub fn with_json_string_fields(self, fields: &[&str]) -> Self {
+        let json_string_fields = fields.iter().map(|field| field.to_string()).collect();
+
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.json_string_fields = json_string_fields;
+            event_formatter
+        })
+    }
+
+    /// Registers transforms applied to the value of every Event field, in order, after any
+    /// configured redactions. Transforms are added to those registered by previous calls.
+    pub fn with_transforms(self, transforms: Vec<Box<dyn crate::FieldTransform>>) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter
+                .transforms
+                .extend(transforms.into_iter().map(Arc::from));
+            event_formatter
+        })
+    }
+
+    /// Configures a pattern for redacting the values of all fields with matching names, in
+    /// addition to any fields configured through `with_redacted_fields`. Nested fields (e.g.
+    /// `labels.api_token`) are redacted when either their full name or their nested key matches.
+    #[cfg_attr(docsrs, doc(cfg(feature = "regex")))]
+    #[cfg(any(docsrs, feature = "regex"))]
+    pub fn with_redaction_pattern(self, pattern: regex::Regex) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.redaction_patterns.push(pattern);
+            event_formatter
+        })
+    }
+
+    /// Configures how `LogDuration` fields recorded through `valuable` are formatted, defaulting
+    /// to Cloud Logging's `"{secs}s"` format. `http_request.latency` always uses the default format,
+    /// as required by the `httpRequest` field.
+    #[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+    #[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+    pub fn with_duration_format(self, duration_format: crate::DurationFormat) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.duration_format = duration_format;
+            event_formatter
+        })
+    }
+
+    /// Configures the maximum nesting depth of values recorded through `valuable`, replacing lists,
+    /// maps, structs, enums and tuples nested any deeper with `{"__truncated": true}`. Guards
+    /// against overflowing the stack when serializing deeply-nested (or cyclic) structures, which
+    /// are otherwise serialized in full.
+    #[cfg_attr(docsrs, doc(cfg(feature = "valuable")))]
+    #[cfg(any(docsrs, all(tracing_unstable, feature = "valuable")))]
+    pub fn with_max_value_depth(self, max_depth: usize) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.max_value_depth = Some(max_depth);
+            event_formatter
+        })
+    }
+
+    /// Configures the format of written entries, e.g. `OutputFormat::Syslog` for relays that expect
+    /// RFC5424 syslog rather than Cloud Logging JSON. Syslog lines are never colorized.
+    #[cfg_attr(docsrs, doc(cfg(feature = "syslog")))]
+    #[cfg(any(docsrs, feature = "syslog"))]
+    pub fn with_format(self, output_format: crate::OutputFormat) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.output_format = output_format;
+            event_formatter
+        })
     }
 
     /// Configures the Cloud Trace integration with OpenTelemetry through special LogEntry fields
     #[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
     #[cfg(any(docsrs, feature = "opentelemetry"))]
     pub fn with_cloud_trace(self, configuration: crate::CloudTraceConfiguration) -> Self {
-        Self(self.0.map_event_format(|mut event_formatter| {
+        self.map_event_format(|mut event_formatter| {
             event_formatter.cloud_trace_configuration = Some(configuration);
             event_formatter
-        }))
+        })
+    }
+
+    /// Configures whether or not the W3C `tracestate` of the parent OpenTelemetry context, when
+    /// present, is included as a `tracestate` label for debugging trace propagation
+    #[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
+    #[cfg(any(docsrs, feature = "opentelemetry"))]
+    pub fn with_trace_state(self, include_trace_state: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.include_trace_state = include_trace_state;
+            event_formatter
+        })
+    }
+
+    /// Configures whether or not the kind (e.g. from an `otel.kind = "server"` span field) and
+    /// status (e.g. from `otel.status_code = "error"`) of the nearest OpenTelemetry span are
+    /// included as `span.kind` and `span.status` labels. Unset statuses are omitted.
+    #[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
+    #[cfg(any(docsrs, feature = "opentelemetry"))]
+    pub fn with_span_kind_and_status(self, include_span_kind_and_status: bool) -> Self {
+        self.map_event_format(|mut event_formatter| {
+            event_formatter.include_span_kind_and_status = include_span_kind_and_status;
+            event_formatter
+        })
     }
 }
 
@@ -87,7 +998,16 @@ where
         id: &tracing_core::span::Id,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        self.0.on_new_span(attrs, id, context)
+        // start times are only read by `startedAt` fields and span summaries
+        if self.1.span_start_times || self.1.span_summaries {
+            if let Some(span) = context.span(id) {
+                span.extensions_mut()
+                    .insert(SpanStartTime(std::time::SystemTime::now()));
+            }
+        }
+
+        self.0.on_new_span(attrs, id, context.clone());
+        self.on_span_event(FmtSpan::NEW, "new", id, context);
     }
 
     fn on_record(
@@ -104,7 +1024,8 @@ where
         id: &tracing_core::span::Id,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        self.0.on_enter(id, context)
+        self.0.on_enter(id, context.clone());
+        self.on_span_event(FmtSpan::ENTER, "enter", id, context);
     }
 
     fn on_exit(
@@ -112,7 +1033,8 @@ where
         id: &tracing_core::span::Id,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
-        self.0.on_exit(id, context)
+        self.0.on_exit(id, context.clone());
+        self.on_span_event(FmtSpan::EXIT, "exit", id, context);
     }
 
     fn on_close(
@@ -120,11 +1042,15 @@ where
         id: tracing_core::span::Id,
         context: tracing_subscriber::layer::Context<'_, S>,
     ) {
+        self.on_span_event(FmtSpan::CLOSE, "close", &id, context.clone());
+        self.on_span_summary(&id, context.clone());
         self.0.on_close(id, context)
     }
 
     fn on_event(&self, event: &Event<'_>, context: tracing_subscriber::layer::Context<'_, S>) {
-        self.0.on_event(event, context)
+        let _guard = crate::panic::LoggingGuard::new();
+
+        self.0.on_event(event, context);
     }
 
     unsafe fn downcast_raw(&self, id: std::any::TypeId) -> Option<*const ()> {
@@ -132,11 +1058,25 @@ where
     }
 }
 
+impl<S, W> Clone for Layer<S, W>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Clone + 'static,
+{
+    fn clone(&self) -> Self {
+        Layer(
+            inner_layer(&self.1, &self.2).with_writer(self.0.writer().clone()),
+            self.1.clone(),
+            self.2.clone(),
+        )
+    }
+}
+
 impl<S, W> Deref for Layer<S, W>
 where
     S: Subscriber + for<'span> LookupSpan<'span>,
 {
-    type Target = tracing_subscriber::fmt::Layer<S, JsonFields, EventFormatter, W>;
+    type Target = InnerLayer<S, W>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -4,12 +4,50 @@
 #![allow(clippy::needless_doctest_main)]
 #![doc = include_str!("../README.md")]
 
+// the time crate is only used for formatting timestamps when `minimal-time` is disabled
+#[cfg(all(feature = "time", feature = "minimal-time"))]
+use time as _;
+
+mod config;
 mod event_formatter;
 mod google;
 mod layer;
+mod log_entry;
+mod macros;
+#[cfg(feature = "opentelemetry")]
+mod metadata;
+#[cfg_attr(docsrs, doc(cfg(feature = "opentelemetry")))]
+#[cfg(feature = "opentelemetry")]
+pub mod opentelemetry;
+mod panic;
 mod serializers;
+#[cfg(feature = "syslog")]
+mod syslog;
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+#[cfg(feature = "testing")]
+pub mod testing;
+mod timestamp;
+#[cfg(feature = "tower")]
+mod tower;
+mod transform;
+#[cfg(all(tracing_unstable, feature = "valuable"))]
+mod value_depth;
 mod visitor;
 mod writer;
 
+pub use self::config::*;
 pub use self::google::*;
 pub use self::layer::*;
+pub use self::log_entry::*;
+pub use self::panic::install_panic_hook;
+#[cfg(feature = "tower")]
+pub use self::tower::*;
+pub use self::transform::*;
+pub use self::writer::{
+    ErrorCounting, ErrorCountingWriter, FlushOnSeverity, FlushOnSeverityWriter, SeverityRouting,
+};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use tracing;
+}
@@ -0,0 +1,181 @@
+use crate::{google::LogSeverity, serializers::SourceLocation};
+use serde::ser::{Serialize, SerializeMap};
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, time::SystemTime};
+
+/// A structured LogEntry in the same shape as the entries emitted by the Layer, which formats
+/// every Event into one of these before serializing it. Entries can also be constructed and
+/// emitted outside of `tracing` (e.g. when replaying stored events).
+/// [See Google's LogEntry docs here](https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry).
+#[derive(Clone, Debug, Default)]
+pub struct LogEntry {
+    /// Time at which the entry occurred, serialized as an RFC3339 timestamp
+    pub time: Option<SystemTime>,
+    /// Time at which the entry was received, serialized as an RFC3339 `receiveTimestamp`
+    pub receive_timestamp: Option<SystemTime>,
+    /// Monitored resource that the entry was written from
+    pub resource: Option<LogEntryResource>,
+    /// Resource name of the log that the entry is written to (e.g.
+    /// `projects/{project_id}/logs/{log_id}`)
+    pub log_name: Option<String>,
+    /// Severity of the entry
+    pub severity: LogSeverity,
+    /// Whether or not the severity is also serialized as a numeric `severityNumber`
+    pub numeric_severity: bool,
+    /// Source code location of the entry
+    pub source_location: Option<LogEntrySourceLocation>,
+    /// Unique identifier of the entry, used by Cloud Logging for deduplication
+    pub insert_id: Option<String>,
+    /// Resource name of the trace associated with the entry (e.g.
+    /// `projects/{project_id}/traces/{trace_id}`)
+    pub trace: Option<String>,
+    /// ID of the span associated with the entry
+    pub span_id: Option<String>,
+    /// Whether or not the trace associated with the entry was sampled
+    pub trace_sampled: bool,
+    /// Message of the entry, serialized ahead of its other fields. Entries formatted by the Layer
+    /// keep their message among `fields` instead, as it's handled like any other Event field.
+    pub message: Option<String>,
+    /// Additional fields of the entry (e.g. Event and span fields), serialized in order with
+    /// their keys as-is
+    pub fields: Vec<(String, Value)>,
+    /// Whether or not the message and fields are nested under `jsonPayload` instead of being
+    /// serialized alongside the entry's special fields
+    pub json_payload: bool,
+    /// Fields of the `httpRequest` special field, keyed by their camelCased names
+    pub http_request: Map<String, Value>,
+    /// User-defined labels of the entry
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Monitored resource of a LogEntry
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct LogEntryResource {
+    /// [Monitored resource type](https://cloud.google.com/logging/docs/api/v2/resource-list)
+    /// (e.g. `cloud_run_revision`)
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    /// Labels identifying the resource (e.g. `project_id`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Source code location of a LogEntry
+#[derive(Clone, Debug, Default)]
+pub struct LogEntrySourceLocation {
+    /// Source file name
+    pub file: String,
+    /// Line within the source file
+    pub line: Option<u32>,
+    /// Human-readable name of the function or method being invoked
+    pub function: Option<String>,
+}
+
+/// The message and fields of a LogEntry, serialized as a map in order
+struct Payload<'e>(&'e LogEntry);
+
+impl Serialize for Payload<'_> {
+    fn serialize<R>(&self, serializer: R) -> Result<R::Ok, R::Error>
+    where
+        R: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        self.0.serialize_payload(&mut map)?;
+        map.end()
+    }
+}
+
+impl LogEntry {
+    fn serialize_payload<M>(&self, map: &mut M) -> Result<(), M::Error>
+    where
+        M: SerializeMap,
+    {
+        if let Some(message) = &self.message {
+            map.serialize_entry("message", message)?;
+        }
+
+        for (key, value) in &self.fields {
+            map.serialize_entry(key, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Serialize for LogEntry {
+    fn serialize<R>(&self, serializer: R) -> Result<R::Ok, R::Error>
+    where
+        R: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+
+        if let Some(time) = self.time {
+            map.serialize_entry("time", &crate::timestamp::format(time))?;
+        }
+
+        if let Some(receive_timestamp) = self.receive_timestamp {
+            map.serialize_entry(
+                "receiveTimestamp",
+                &crate::timestamp::format(receive_timestamp),
+            )?;
+        }
+
+        if let Some(resource) = &self.resource {
+            map.serialize_entry("resource", resource)?;
+        }
+
+        if let Some(log_name) = &self.log_name {
+            map.serialize_entry("logName", log_name)?;
+        }
+
+        map.serialize_entry("severity", &self.severity)?;
+
+        if self.numeric_severity {
+            map.serialize_entry("severityNumber", &self.severity.as_number())?;
+        }
+
+        if let Some(source_location) = &self.source_location {
+            map.serialize_entry(
+                "logging.googleapis.com/sourceLocation",
+                &SourceLocation {
+                    file: &source_location.file,
+                    line: source_location.line,
+                    function: source_location.function.as_deref(),
+                },
+            )?;
+        }
+
+        if let Some(insert_id) = &self.insert_id {
+            map.serialize_entry("logging.googleapis.com/insertId", insert_id)?;
+        }
+
+        if let Some(trace) = &self.trace {
+            map.serialize_entry("logging.googleapis.com/trace", trace)?;
+        }
+
+        if let Some(span_id) = &self.span_id {
+            map.serialize_entry("logging.googleapis.com/spanId", span_id)?;
+        }
+
+        // trace_sampled is only meaningful when true, so it's omitted otherwise
+        if self.trace_sampled {
+            map.serialize_entry("logging.googleapis.com/trace_sampled", &true)?;
+        }
+
+        if self.json_payload {
+            map.serialize_entry("jsonPayload", &Payload(self))?;
+        } else {
+            self.serialize_payload(&mut map)?;
+        }
+
+        if !self.http_request.is_empty() {
+            map.serialize_entry("httpRequest", &self.http_request)?;
+        }
+
+        if !self.labels.is_empty() {
+            map.serialize_entry("logging.googleapis.com/labels", &self.labels)?;
+        }
+
+        map.end()
+    }
+}
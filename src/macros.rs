@@ -0,0 +1,36 @@
+/// Emits a tracing Event with a strongly-typed [`LogSeverity`](crate::LogSeverity), followed by
+/// fields and a message (with any format arguments) just like tracing's own event macros:
+///
+/// ```
+/// use tracing_stackdriver::{stackdriver_event, LogSeverity};
+///
+/// let path = "config.toml";
+/// stackdriver_event!(LogSeverity::Notice, version = 2, "reloaded {path} in {}ms", 12);
+/// ```
+///
+/// The Event is emitted at the closest tracing `Level` (e.g. `INFO` for `Notice`) so that
+/// level-based filters still apply, with its severity overridden through a `severity` field.
+#[macro_export]
+macro_rules! stackdriver_event {
+    ($severity:expr, $($arg:tt)+) => {{
+        use $crate::__private::tracing::{event, Level};
+
+        let severity: $crate::LogSeverity = $severity;
+
+        match severity.to_level_filter().into_level() {
+            Some(Level::ERROR) => {
+                event!(Level::ERROR, severity = %severity, $($arg)+)
+            }
+            Some(Level::WARN) => {
+                event!(Level::WARN, severity = %severity, $($arg)+)
+            }
+            Some(Level::INFO) => {
+                event!(Level::INFO, severity = %severity, $($arg)+)
+            }
+            Some(Level::DEBUG) => {
+                event!(Level::DEBUG, severity = %severity, $($arg)+)
+            }
+            _ => event!(Level::TRACE, severity = %severity, $($arg)+),
+        }
+    }};
+}
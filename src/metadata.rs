@@ -0,0 +1,249 @@
+use std::{
+    env,
+    io::{self, Read, Write},
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+/// Default hostname of the Google Compute Engine metadata server, sent as the `Host` header
+const METADATA_HOST: &str = "metadata.google.internal";
+
+/// Fixed link-local address of the metadata server, connected to directly so that the default
+/// host is never resolved through DNS
+const METADATA_ADDRESS: SocketAddr =
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(169, 254, 169, 254)), 80);
+
+/// Environment variable used by Google client libraries to override the metadata server host
+const METADATA_HOST_VARIABLE: &str = "GCE_METADATA_HOST";
+
+/// Upper bound for connecting to and reading the whole response from the metadata server, kept
+/// short so that lookups fail quickly when not running on GCP
+const METADATA_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Fetch the current project ID from the metadata server
+pub(crate) fn project_id() -> io::Result<String> {
+    get("/computeMetadata/v1/project/project-id")
+}
+
+/// Finds the address of the metadata server and the host to request it with. Only hostnames
+/// overriding the default host are resolved, through the system resolver (which isn't bounded by
+/// the metadata server timeout), while IP addresses are connected to directly.
+fn address() -> io::Result<(SocketAddr, String)> {
+    let host = match env::var(METADATA_HOST_VARIABLE) {
+        Ok(host) => host,
+        Err(_) => return Ok((METADATA_ADDRESS, METADATA_HOST.to_owned())),
+    };
+
+    if let Ok(socket_address) = host.parse::<SocketAddr>() {
+        return Ok((socket_address, host));
+    }
+
+    if let Ok(ip_address) = host.parse::<IpAddr>() {
+        return Ok((SocketAddr::new(ip_address, 80), host));
+    }
+
+    let address = if host.contains(':') {
+        host.clone()
+    } else {
+        format!("{host}:80")
+    };
+
+    match address.to_socket_addrs()?.next() {
+        Some(socket_address) => Ok((socket_address, host)),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Could not resolve metadata server at {host}"),
+        )),
+    }
+}
+
+/// Issue a blocking GET request against the metadata server, returning the response body
+fn get(path: &str) -> io::Result<String> {
+    let (socket_address, host) = address()?;
+
+    request(socket_address, &host, path, METADATA_TIMEOUT)
+}
+
+/// Issue a blocking GET request against a server, failing if the whole response hasn't been read
+/// before the timeout elapses (even when the server keeps sending data)
+fn request(
+    socket_address: SocketAddr,
+    host: &str,
+    path: &str,
+    timeout: Duration,
+) -> io::Result<String> {
+    let deadline = Instant::now() + timeout;
+
+    let mut stream = TcpStream::connect_timeout(&socket_address, timeout)?;
+    stream.set_write_timeout(Some(remaining(deadline)?))?;
+
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nMetadata-Flavor: Google\r\nConnection: close\r\n\r\n"
+    )?;
+
+    let mut response = Vec::new();
+    let mut buffer = [0; 1024];
+
+    loop {
+        stream.set_read_timeout(Some(remaining(deadline)?))?;
+
+        match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => response.extend_from_slice(&buffer[..read]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            // read timeouts are reported as `WouldBlock` on some platforms
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Err(timed_out())
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    let response = String::from_utf8(response).map_err(|_| malformed())?;
+
+    parse_response(&response)
+}
+
+/// Returns the time left before a deadline, failing once it has passed
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    deadline
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+        .ok_or_else(timed_out)
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        "Timed out waiting for metadata server",
+    )
+}
+
+/// Extracts the body of a successful HTTP/1.1 response, decoding chunked bodies
+fn parse_response(response: &str) -> io::Result<String> {
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(malformed)?;
+    let mut lines = head.lines();
+
+    match lines
+        .next()
+        .and_then(|status| status.split_whitespace().nth(1))
+    {
+        Some("200") => {}
+        status => {
+            return Err(io::Error::other(format!(
+                "Unexpected metadata server status: {}",
+                status.unwrap_or("unknown")
+            )))
+        }
+    }
+
+    let chunked = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+
+    if chunked {
+        decode_chunked(body).map(|body| body.trim().to_owned())
+    } else {
+        Ok(body.trim().to_owned())
+    }
+}
+
+/// Concatenates the chunks of a body with chunked transfer encoding, ignoring chunk extensions
+/// and trailers
+fn decode_chunked(mut body: &str) -> io::Result<String> {
+    let mut decoded = String::new();
+
+    loop {
+        let (size_line, rest) = body.split_once("\r\n").ok_or_else(malformed)?;
+        let size = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| malformed())?;
+
+        if size == 0 {
+            return Ok(decoded);
+        }
+
+        let chunk = rest.get(..size).ok_or_else(malformed)?;
+        decoded.push_str(chunk);
+        body = rest[size..].strip_prefix("\r\n").ok_or_else(malformed)?;
+    }
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Malformed response from metadata server",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_response, request};
+    use std::{
+        io::{self, Write},
+        net::TcpListener,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn reads_content_length_bodies() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nmy-project\n";
+
+        assert_eq!(parse_response(response).unwrap(), "my-project");
+    }
+
+    #[test]
+    fn decodes_chunked_bodies() {
+        let response = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3;ext=1\r\nmy-\r\n7\r\nproject\r\n0\r\n\r\n";
+
+        assert_eq!(parse_response(response).unwrap(), "my-project");
+    }
+
+    #[test]
+    fn rejects_truncated_chunks() {
+        let response = "HTTP/1.1 200 OK\r\ntransfer-encoding: Chunked\r\n\r\na\r\nmy-\r\n";
+
+        assert!(parse_response(response).is_err());
+    }
+
+    #[test]
+    fn rejects_unsuccessful_responses() {
+        let response = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+
+        assert!(parse_response(response).is_err());
+    }
+
+    #[test]
+    fn times_out_slow_responses() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // a server that keeps trickling out a response, never finishing it
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            for byte in "HTTP/1.1 200 OK\r\n".bytes().cycle().take(100) {
+                if stream.write_all(&[byte]).is_err() {
+                    break;
+                }
+
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        let start = Instant::now();
+        let error = request(address, "localhost", "/", Duration::from_millis(200)).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
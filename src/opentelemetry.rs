@@ -0,0 +1,56 @@
+//! Preconfigured subscribers that combine OpenTelemetry and Stackdriver layers
+use crate::CloudTraceConfiguration;
+use tracing_core::Subscriber;
+use tracing_opentelemetry::PreSampledTracer;
+use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan};
+
+/// Builds a subscriber that exports spans through an OpenTelemetry tracer and writes
+/// Stackdriver-compatible entries with Cloud Trace fields to stdout.
+///
+/// The OpenTelemetry layer is registered first, so that span contexts are available to the
+/// Stackdriver layer by the time Events are formatted.
+///
+/// ```
+/// use opentelemetry::trace::TracerProvider;
+/// use tracing_stackdriver::CloudTraceConfiguration;
+///
+/// fn main() {
+///     let tracer = opentelemetry_sdk::trace::TracerProvider::default().tracer("app");
+///     let subscriber = tracing_stackdriver::opentelemetry::subscriber(
+///         tracer,
+///         CloudTraceConfiguration::new("my-project-id"),
+///     );
+///
+///     tracing::subscriber::set_global_default(subscriber).expect("Could not set up global logger");
+/// }
+/// ```
+pub fn subscriber<T>(
+    tracer: T,
+    configuration: CloudTraceConfiguration,
+) -> impl Subscriber + for<'span> LookupSpan<'span> + Send + Sync
+where
+    T: ::opentelemetry::trace::Tracer + PreSampledTracer + Send + Sync + 'static,
+    T::Span: Send + Sync,
+{
+    subscriber_with_writer(tracer, configuration, std::io::stdout)
+}
+
+/// Builds a subscriber like [`subscriber`], writing entries to the given writer instead of stdout
+pub fn subscriber_with_writer<T, M>(
+    tracer: T,
+    configuration: CloudTraceConfiguration,
+    make_writer: M,
+) -> impl Subscriber + for<'span> LookupSpan<'span> + Send + Sync
+where
+    T: ::opentelemetry::trace::Tracer + PreSampledTracer + Send + Sync + 'static,
+    T::Span: Send + Sync,
+    M: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .with(
+            crate::layer()
+                .with_writer(make_writer)
+                .with_cloud_trace(configuration),
+        )
+}
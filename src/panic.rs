@@ -0,0 +1,82 @@
+use std::{cell::Cell, panic::PanicHookInfo};
+
+/// `@type` of entries that Error Reporting groups into errors
+const REPORTED_ERROR_EVENT_TYPE: &str =
+    "type.googleapis.com/google.devtools.clouderrorreporting.v1beta1.ReportedErrorEvent";
+
+thread_local! {
+    // whether the current thread is emitting an entry, whether through a Layer or the panic hook
+    static LOGGING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as emitting an entry until dropped, even when unwinding
+pub(crate) struct LoggingGuard {
+    was_logging: bool,
+}
+
+impl LoggingGuard {
+    pub(crate) fn new() -> Self {
+        Self {
+            was_logging: LOGGING.with(|logging| logging.replace(true)),
+        }
+    }
+
+    fn was_logging(&self) -> bool {
+        self.was_logging
+    }
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        LOGGING.with(|logging| logging.set(self.was_logging));
+    }
+}
+
+/// Installs a panic hook that reports panics as `ERROR` entries through the current `tracing`
+/// subscriber, with the panic message, location and backtrace in the entry's `message` and the
+/// `@type` that Cloud Error Reporting expects of reported errors. The previously-installed hook
+/// (e.g. the default hook that prints to stderr) is always run afterwards, so panics aren't lost
+/// when no subscriber is installed or `ERROR` entries are filtered out. Panics raised while an
+/// entry is being emitted (e.g. by a writer) are only handed to the previous hook, since logging
+/// them could recurse or deadlock.
+pub fn install_panic_hook() {
+    let previous_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let guard = LoggingGuard::new();
+
+        if !guard.was_logging() {
+            tracing::error!(
+                type_url = REPORTED_ERROR_EVENT_TYPE,
+                "{}",
+                panic_message(info)
+            );
+        }
+
+        drop(guard);
+        previous_hook(info);
+    }));
+}
+
+/// Formats a panic like the default hook does, followed by a backtrace
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    let payload = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+
+    let thread = std::thread::current();
+    let thread = thread.name().unwrap_or("<unnamed>");
+
+    let location = info
+        .location()
+        .map(|location| format!(" at {location}"))
+        .unwrap_or_default();
+
+    format!(
+        "thread '{thread}' panicked{location}:\n{payload}\n\n{}",
+        std::backtrace::Backtrace::force_capture()
+    )
+}
@@ -1,13 +1,50 @@
+use crate::{event_formatter::EventFormatter, visitor::REDACTED};
 use serde::ser::{Serialize, SerializeMap, SerializeSeq};
-use serde_json::Value;
-use tracing_core::Subscriber;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, time::SystemTime};
 use tracing_subscriber::{
-    fmt::{format::JsonFields, FmtContext, FormattedFields},
+    fmt::{format::JsonFields, FormattedFields},
     registry::{LookupSpan, SpanRef},
 };
 
+/// Checks whether or not a span field is nested under a special field of the entry (i.e. labels
+/// and HTTP request fields) instead of the span itself
+pub(crate) fn is_special_span_field(key: &str) -> bool {
+    key.starts_with("labels.") || key.starts_with("http_request.")
+}
+
+/// Span extension recording the time at which a span was created
+pub(crate) struct SpanStartTime(pub(crate) SystemTime);
+
+/// Parses the JSON-formatted fields recorded on a span, replacing the values of redacted fields
+pub(crate) fn span_fields<S>(span: &SpanRef<'_, S>, config: &EventFormatter) -> Map<String, Value>
+where
+    S: for<'lookup> LookupSpan<'lookup>,
+{
+    let extensions = span.extensions();
+
+    let formatted_fields = extensions
+        .get::<FormattedFields<JsonFields>>()
+        .expect("No fields!");
+
+    match serde_json::from_str::<Value>(formatted_fields) {
+        // handle string escaping "properly" (this should be fixed upstream)
+        // https://github.com/tokio-rs/tracing/issues/391
+        Ok(Value::Object(fields)) => fields
+            .into_iter()
+            .map(|(key, value)| match config.is_redacted(&key) {
+                true => (key, Value::from(REDACTED)),
+                false => (key, value),
+            })
+            .collect(),
+        // these two options should be impossible
+        Ok(value) => panic!("Invalid value: {}", value),
+        Err(error) => panic!("Error parsing logs: {}", error),
+    }
+}
+
 /// Serializable tracing span for nesting formatted event fields
-pub(crate) struct SerializableSpan<'a, 'b, S>(&'b SpanRef<'a, S>)
+pub(crate) struct SerializableSpan<'a, 'b, S>(&'b SpanRef<'a, S>, &'b EventFormatter)
 where
     S: for<'lookup> LookupSpan<'lookup>;
 
@@ -15,8 +52,8 @@ impl<'a, 'b, S> SerializableSpan<'a, 'b, S>
 where
     S: for<'lookup> LookupSpan<'lookup>,
 {
-    pub(crate) fn new(span: &'b SpanRef<'a, S>) -> Self {
-        Self(span)
+    pub(crate) fn new(span: &'b SpanRef<'a, S>, config: &'b EventFormatter) -> Self {
+        Self(span, config)
     }
 }
 
@@ -29,50 +66,51 @@ where
         R: serde::Serializer,
     {
         let name = self.0.name();
+        let fields = span_fields(self.0, self.1);
         let extensions = self.0.extensions();
 
-        let formatted_fields = extensions
-            .get::<FormattedFields<JsonFields>>()
-            .expect("No fields!");
+        let started_at = extensions
+            .get::<SpanStartTime>()
+            .filter(|_| self.1.span_start_times)
+            .map(|SpanStartTime(started_at)| crate::timestamp::format(*started_at));
 
-        let span_length = formatted_fields.fields.len() + 1;
-        let mut map = serializer.serialize_map(Some(span_length))?;
+        let mut map = serializer.serialize_map(Some(fields.len() + 1))?;
 
-        match serde_json::from_str::<Value>(formatted_fields) {
-            // handle string escaping "properly" (this should be fixed upstream)
-            // https://github.com/tokio-rs/tracing/issues/391
-            Ok(Value::Object(fields)) => {
-                for (key, value) in fields {
-                    map.serialize_entry(&key, &value)?;
-                }
+        for (key, value) in fields {
+            // labels and HTTP request fields are nested under their own special fields
+            if !is_special_span_field(&key) {
+                map.serialize_entry(&self.1.span_field_casing.apply(&key), &value)?;
             }
-            // these two options should be impossible
-            Ok(value) => panic!("Invalid value: {}", value),
-            Err(error) => panic!("Error parsing logs: {}", error),
-        };
+        }
 
         map.serialize_entry("name", &name)?;
+
+        if let Some(started_at) = started_at {
+            map.serialize_entry("startedAt", &started_at)?;
+        }
+
         map.end()
     }
 }
 
-/// Serializable tracing context for serializing a collection of spans
-pub(crate) struct SerializableContext<'a, 'b, S>(&'b FmtContext<'a, S, JsonFields>)
+/// Serializable tracing context for serializing a span and its ancestors, from the root span
+/// down to the span itself
+pub(crate) struct SerializableContext<'a, 'b, S>(&'b SpanRef<'a, S>, &'b EventFormatter)
 where
-    S: Subscriber + for<'lookup> LookupSpan<'lookup>;
+    S: for<'lookup> LookupSpan<'lookup>;
 
 impl<'a, 'b, S> SerializableContext<'a, 'b, S>
 where
-    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    S: for<'lookup> LookupSpan<'lookup>,
 {
-    pub(crate) fn new(context: &'b FmtContext<'a, S, JsonFields>) -> Self {
-        Self(context)
+    pub(crate) fn new(leaf_span: &'b SpanRef<'a, S>, config: &'b EventFormatter) -> Self {
+        Self(leaf_span, config)
     }
 }
 
 impl<'a, 'b, S> Serialize for SerializableContext<'a, 'b, S>
 where
-    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    S: for<'lookup> LookupSpan<'lookup>,
 {
     fn serialize<R>(&self, serializer: R) -> Result<R::Ok, R::Error>
     where
@@ -80,19 +118,69 @@ where
     {
         let mut list = serializer.serialize_seq(None)?;
 
-        if let Some(leaf_span) = self.0.lookup_current() {
-            for span in leaf_span.scope().from_root() {
-                list.serialize_element(&SerializableSpan::new(&span))?;
-            }
+        for span in self.0.scope().from_root() {
+            list.serialize_element(&SerializableSpan::new(&span, self.1))?;
         }
 
         list.end()
     }
 }
 
+/// Serializable fields of a span and its ancestors, keyed by span name. Spans that share a name
+/// with one of their ancestors are keyed with an index suffix (e.g. `handler_1`).
+pub(crate) struct SerializableSpanFields<'a, 'b, S>(&'b SpanRef<'a, S>, &'b EventFormatter)
+where
+    S: for<'lookup> LookupSpan<'lookup>;
+
+impl<'a, 'b, S> SerializableSpanFields<'a, 'b, S>
+where
+    S: for<'lookup> LookupSpan<'lookup>,
+{
+    pub(crate) fn new(span: &'b SpanRef<'a, S>, config: &'b EventFormatter) -> Self {
+        Self(span, config)
+    }
+}
+
+impl<'a, 'b, S> Serialize for SerializableSpanFields<'a, 'b, S>
+where
+    S: for<'lookup> LookupSpan<'lookup>,
+{
+    fn serialize<R>(&self, serializer: R) -> Result<R::Ok, R::Error>
+    where
+        R: serde::Serializer,
+    {
+        let mut name_counts = HashMap::new();
+        let mut map = serializer.serialize_map(None)?;
+
+        for span in self.0.scope().from_root() {
+            let name = span.name();
+            let count = name_counts.entry(name).or_insert(0);
+
+            let key = match *count {
+                0 => name.to_owned(),
+                index => format!("{name}_{index}"),
+            };
+
+            *count += 1;
+
+            // labels and HTTP request fields are nested under their own special fields
+            let fields: Map<String, Value> = span_fields(&span, self.1)
+                .into_iter()
+                .filter(|(key, _)| !is_special_span_field(key))
+                .map(|(key, value)| (self.1.span_field_casing.apply(&key), value))
+                .collect();
+
+            map.serialize_entry(&key, &fields)?;
+        }
+
+        map.end()
+    }
+}
+
 pub(crate) struct SourceLocation<'a> {
     pub(crate) file: &'a str,
     pub(crate) line: Option<u32>,
+    pub(crate) function: Option<&'a str>,
 }
 
 impl<'a> Serialize for SourceLocation<'a> {
@@ -100,13 +188,17 @@ impl<'a> Serialize for SourceLocation<'a> {
     where
         R: serde::Serializer,
     {
-        let mut map = serializer.serialize_map(Some(if self.line.is_some() { 2 } else { 1 }))?;
+        let length = 1 + usize::from(self.line.is_some()) + usize::from(self.function.is_some());
+        let mut map = serializer.serialize_map(Some(length))?;
         map.serialize_entry("file", self.file)?;
         if let Some(line) = self.line {
             // Stackdriver expects the line number to be serialised as a string:
             // https://cloud.google.com/logging/docs/reference/v2/rest/v2/LogEntry#LogEntrySourceLocation
             map.serialize_entry("line", &line.to_string())?;
         }
+        if let Some(function) = self.function {
+            map.serialize_entry("function", function)?;
+        }
         map.end()
     }
 }
@@ -0,0 +1,122 @@
+use crate::google::LogSeverity;
+
+/// Syslog facility of every entry (`user`)
+const FACILITY: u8 = 1;
+
+/// Structured data ID of the element holding entry fields, under the enterprise number reserved
+/// for documentation by RFC5424
+const SD_ID: &str = "fields@32473";
+
+/// Prefix of Cloud Logging special fields, stripped from structured data parameter names
+const SPECIAL_FIELD_PREFIX: &str = "logging.googleapis.com/";
+
+/// Formatter converting the JSON entries produced by an `EventFormatter` into RFC5424 syslog lines
+/// for relays that don't accept Cloud Logging JSON. The `message` of each entry becomes the MSG,
+/// its `target` the APP-NAME, and its remaining fields parameters of a single structured data
+/// element, with nested objects serialized as JSON.
+pub(crate) struct SyslogFormatter;
+
+impl SyslogFormatter {
+    /// Formats an entry as a syslog line, without a trailing line ending
+    pub(crate) fn format(&self, mut entry: serde_json::Map<String, serde_json::Value>) -> String {
+        let severity = entry
+            .remove("severity")
+            .map(LogSeverity::from)
+            .unwrap_or_default();
+        entry.remove("severityNumber");
+
+        let timestamp = match entry.remove("time") {
+            Some(serde_json::Value::String(time)) => truncate_fraction(&time),
+            _ => "-".to_owned(),
+        };
+
+        let app_name = match entry.remove("target") {
+            Some(serde_json::Value::String(target)) if !target.is_empty() => {
+                header_field(&target, 48)
+            }
+            _ => "-".to_owned(),
+        };
+
+        let message = match entry.remove("message") {
+            Some(serde_json::Value::String(message)) => message,
+            Some(message) => message.to_string(),
+            None => String::new(),
+        };
+
+        let structured_data = if entry.is_empty() {
+            "-".to_owned()
+        } else {
+            let parameters: String = entry
+                .into_iter()
+                .map(|(key, value)| {
+                    let name = key.strip_prefix(SPECIAL_FIELD_PREFIX).unwrap_or(&key);
+                    let value = match value {
+                        serde_json::Value::String(value) => value,
+                        value => value.to_string(),
+                    };
+
+                    format!(" {}=\"{}\"", parameter_name(name), escape(&value))
+                })
+                .collect();
+
+            format!("[{SD_ID}{parameters}]")
+        };
+
+        let mut line = format!(
+            "<{}>1 {timestamp} - {app_name} {} - {structured_data}",
+            FACILITY * 8 + severity.as_syslog_severity(),
+            std::process::id(),
+        );
+
+        if !message.is_empty() {
+            line.push(' ');
+            line.push_str(&message);
+        }
+
+        line
+    }
+}
+
+/// Truncates the fractional seconds of an RFC3339 timestamp to the microseconds allowed by syslog
+fn truncate_fraction(time: &str) -> String {
+    match time.split_once('.') {
+        Some((seconds, fraction)) => {
+            let digits = fraction
+                .find(|character: char| !character.is_ascii_digit())
+                .unwrap_or(fraction.len());
+            let (digits, offset) = fraction.split_at(digits);
+
+            format!("{seconds}.{}{offset}", &digits[..digits.len().min(6)])
+        }
+        None => time.to_owned(),
+    }
+}
+
+/// Restricts a header field to its maximum length of printable ASCII characters
+fn header_field(value: &str, max_length: usize) -> String {
+    value
+        .chars()
+        .map(|character| {
+            if character.is_ascii_graphic() {
+                character
+            } else {
+                '_'
+            }
+        })
+        .take(max_length)
+        .collect()
+}
+
+/// Restricts a structured data parameter name to the 32 printable ASCII characters allowed,
+/// excluding `=`, `]` and `"`
+fn parameter_name(name: &str) -> String {
+    header_field(name, 32).replace(['=', ']', '"'], "_")
+}
+
+/// Escapes the characters that terminate structured data parameter values
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
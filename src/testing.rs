@@ -0,0 +1,164 @@
+//! Helpers for testing the entries emitted by a Layer, enabled by the `testing` feature
+
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Maximum number of unread entries kept by a `TestWriter`, after which the oldest are dropped
+const CAPACITY: usize = 1024;
+
+/// Unread entries shared between a `TestWriter` and its `TestReader`
+#[derive(Debug, Default)]
+struct Entries {
+    queue: Mutex<VecDeque<serde_json::Value>>,
+    written: Condvar,
+}
+
+impl Entries {
+    fn push(&self, entry: serde_json::Value) {
+        let mut queue = self.queue.lock().unwrap_or_else(|error| error.into_inner());
+
+        if queue.len() == CAPACITY {
+            queue.pop_front();
+        }
+
+        queue.push_back(entry);
+        self.written.notify_all();
+    }
+}
+
+/// MakeWriter that parses the entries written by a Layer into an in-memory ring buffer, created
+/// through `Layer::with_writer_make_for_test`
+#[derive(Clone, Debug)]
+pub struct TestWriter(Arc<Entries>);
+
+/// Reader for the entries captured by a `TestWriter`, which can wait for entries written from
+/// other threads (e.g. background tasks) as they're written
+#[derive(Clone, Debug)]
+pub struct TestReader(Arc<Entries>);
+
+/// Creates a connected `TestWriter` and `TestReader`
+pub(crate) fn channel() -> (TestWriter, TestReader) {
+    let entries = Arc::new(Entries::default());
+
+    (TestWriter(entries.clone()), TestReader(entries))
+}
+
+impl<'a> MakeWriter<'a> for TestWriter {
+    type Writer = TestEntryWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        TestEntryWriter {
+            entries: self.0.clone(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// Writer returned by `TestWriter`, which parses each complete line as an entry
+#[derive(Debug)]
+pub struct TestEntryWriter {
+    entries: Arc<Entries>,
+    buffer: Vec<u8>,
+}
+
+impl io::Write for TestEntryWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        while let Some(index) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=index).collect();
+            let entry = serde_json::from_slice(&line)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+            self.entries.push(entry);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl TestReader {
+    /// Returns the oldest unread entry, if any
+    pub fn try_recv(&self) -> Option<serde_json::Value> {
+        self.0
+            .queue
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .pop_front()
+    }
+
+    /// Waits up to `timeout` for an entry to be written, returning the oldest unread entry
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<serde_json::Value> {
+        let queue = self
+            .0
+            .queue
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+
+        let (mut queue, _) = self
+            .0
+            .written
+            .wait_timeout_while(queue, timeout, |queue| queue.is_empty())
+            .unwrap_or_else(|error| error.into_inner());
+
+        queue.pop_front()
+    }
+}
+
+/// Formats an Event within a span whose remote OpenTelemetry parent has the given trace and span
+/// IDs, returning the parsed entry. Spans are created with a no-op tracer, so no `TracerProvider`
+/// or exporter is required.
+#[cfg_attr(docsrs, doc(cfg(all(feature = "testing", feature = "opentelemetry"))))]
+#[cfg(feature = "opentelemetry")]
+pub fn format_with_trace_context(
+    configuration: crate::CloudTraceConfiguration,
+    trace_id: opentelemetry::trace::TraceId,
+    span_id: opentelemetry::trace::SpanId,
+    sampled: bool,
+) -> serde_json::Map<String, serde_json::Value> {
+    use opentelemetry::trace::{SpanContext, TraceContextExt, TraceFlags, TraceState};
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    let (layer, reader) = crate::layer()
+        .with_cloud_trace(configuration)
+        .with_writer_make_for_test();
+
+    let subscriber = Registry::default()
+        .with(tracing_opentelemetry::layer())
+        .with(layer);
+
+    let trace_flags = if sampled {
+        TraceFlags::SAMPLED
+    } else {
+        TraceFlags::default()
+    };
+
+    let context = opentelemetry::Context::new().with_remote_span_context(SpanContext::new(
+        trace_id,
+        span_id,
+        trace_flags,
+        true,
+        TraceState::default(),
+    ));
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("test");
+        span.set_parent(context);
+        span.in_scope(|| tracing::info!("test event"));
+    });
+
+    match reader.try_recv() {
+        Some(serde_json::Value::Object(entry)) => entry,
+        entry => panic!("Expected a single entry, found {entry:?}"),
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::{Duration, SystemTime};
+
+/// Formats a time as an RFC3339 timestamp in UTC (e.g. `2024-01-02T03:04:05.123Z`)
+#[cfg(all(feature = "time", not(feature = "minimal-time")))]
+pub(crate) fn format(timestamp: SystemTime) -> String {
+    time::OffsetDateTime::from(timestamp)
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_else(|_| format_minimal(timestamp))
+}
+
+/// Formats a time as an RFC3339 timestamp in UTC (e.g. `2024-01-02T03:04:05.123Z`)
+#[cfg(not(all(feature = "time", not(feature = "minimal-time"))))]
+pub(crate) fn format(timestamp: SystemTime) -> String {
+    format_minimal(timestamp)
+}
+
+/// Returns the number of nanoseconds between the Unix epoch and a time, which is negative for
+/// times before the epoch
+pub(crate) fn unix_timestamp_nanos(timestamp: SystemTime) -> i128 {
+    match timestamp.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(duration) => duration.as_nanos() as i128,
+        Err(error) => -(error.duration().as_nanos() as i128),
+    }
+}
+
+/// Converts a number of nanoseconds since the Unix epoch into a time, if representable
+#[cfg_attr(not(all(tracing_unstable, feature = "valuable")), allow(dead_code))]
+pub(crate) fn from_unix_timestamp_nanos(unix_timestamp_nanos: i128) -> Option<SystemTime> {
+    let nanos = unix_timestamp_nanos.unsigned_abs();
+    let duration = Duration::new(
+        u64::try_from(nanos / 1_000_000_000).ok()?,
+        (nanos % 1_000_000_000) as u32,
+    );
+
+    if unix_timestamp_nanos >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(duration)
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(duration)
+    }
+}
+
+/// Formats a time as an RFC3339 timestamp without the `time` crate, matching its output
+/// byte-for-byte: fractional seconds are only included when non-zero, without trailing zeros
+fn format_minimal(timestamp: SystemTime) -> String {
+    let unix_timestamp_nanos = unix_timestamp_nanos(timestamp);
+    let seconds = unix_timestamp_nanos.div_euclid(1_000_000_000) as i64;
+    let nanos = unix_timestamp_nanos.rem_euclid(1_000_000_000) as u32;
+
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let second_of_day = seconds.rem_euclid(86_400);
+
+    let mut output = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        second_of_day / 3_600,
+        second_of_day % 3_600 / 60,
+        second_of_day % 60,
+    );
+
+    if nanos != 0 {
+        let fraction = format!("{nanos:09}");
+        output.push('.');
+        output.push_str(fraction.trim_end_matches('0'));
+    }
+
+    output.push('Z');
+    output
+}
+
+/// Converts a number of days since the Unix epoch into a proleptic Gregorian (year, month, day)
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_minimal, from_unix_timestamp_nanos, unix_timestamp_nanos};
+    use std::time::{Duration, SystemTime};
+    use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+    #[test]
+    fn matches_time_crate_output() {
+        let instants = [
+            SystemTime::UNIX_EPOCH,
+            SystemTime::UNIX_EPOCH - Duration::from_nanos(1),
+            SystemTime::UNIX_EPOCH - Duration::from_secs(86_400 * 365 * 70),
+            SystemTime::UNIX_EPOCH + Duration::new(951_782_400, 0), // 2000-02-29
+            SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 100_000_000),
+            SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789),
+            SystemTime::UNIX_EPOCH + Duration::new(4_102_444_799, 999_999_990), // 2099-12-31
+            SystemTime::now(),
+        ];
+
+        for instant in instants {
+            let expected = OffsetDateTime::from(instant).format(&Rfc3339).unwrap();
+            assert_eq!(format_minimal(instant), expected);
+        }
+    }
+
+    #[test]
+    fn round_trips_unix_timestamp_nanos() {
+        for instant in [
+            SystemTime::UNIX_EPOCH,
+            SystemTime::UNIX_EPOCH - Duration::new(12, 345),
+            SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789),
+        ] {
+            assert_eq!(
+                from_unix_timestamp_nanos(unix_timestamp_nanos(instant)),
+                Some(instant)
+            );
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+use tower_http::trace::{MakeSpan, OnResponse};
+use tracing::{field::Empty, Span};
+
+/// Reads a header of a request or response as a string, if present and valid
+fn header(headers: &http::HeaderMap, name: http::header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// `MakeSpan` implementation for `tower_http::trace::TraceLayer` that records request data as
+/// `http_request.*` fields of a `request` span. Every entry within the span includes these fields
+/// under the `httpRequest` special field, rather than under `span` or `spans`.
+#[cfg_attr(docsrs, doc(cfg(feature = "tower")))]
+#[derive(Clone, Debug, Default)]
+pub struct StackdriverMakeSpan;
+
+impl<B> MakeSpan<B> for StackdriverMakeSpan {
+    fn make_span(&mut self, request: &http::Request<B>) -> Span {
+        let headers = request.headers();
+
+        tracing::info_span!(
+            "request",
+            http_request.request_method = %request.method(),
+            http_request.request_url = %request.uri(),
+            http_request.protocol = ?request.version(),
+            http_request.user_agent = header(headers, http::header::USER_AGENT),
+            http_request.referer = header(headers, http::header::REFERER),
+            http_request.status = Empty,
+            http_request.latency = Empty,
+            http_request.response_size = Empty,
+        )
+    }
+}
+
+/// `OnResponse` implementation for `tower_http::trace::TraceLayer` that records response data on
+/// spans created by `StackdriverMakeSpan`, then emits an Event with the complete `httpRequest`
+#[cfg_attr(docsrs, doc(cfg(feature = "tower")))]
+#[derive(Clone, Debug, Default)]
+pub struct StackdriverOnResponse;
+
+impl<B> OnResponse<B> for StackdriverOnResponse {
+    fn on_response(self, response: &http::Response<B>, latency: Duration, span: &Span) {
+        let response_size = header(response.headers(), http::header::CONTENT_LENGTH)
+            .and_then(|content_length| content_length.parse::<u64>().ok());
+
+        span.record("http_request.status", response.status().as_u16());
+        span.record(
+            "http_request.latency",
+            format!("{}s", latency.as_secs_f64()).as_str(),
+        );
+
+        if let Some(response_size) = response_size {
+            span.record("http_request.response_size", response_size);
+        }
+
+        tracing::info!(parent: span, "finished processing request");
+    }
+}
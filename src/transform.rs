@@ -0,0 +1,50 @@
+use crate::visitor::REDACTED;
+
+/// Transformation of Event field values, applied to every field in the order registered through
+/// `Layer::with_transforms` before fields are routed into special fields or the payload.
+/// Transforms receive the original field name (e.g. `labels.user_id` or `http_request.status`)
+/// before any casing is applied.
+pub trait FieldTransform: Send + Sync {
+    /// Transforms the value of a field in place
+    fn transform(&self, name: &str, value: &mut serde_json::Value);
+}
+
+impl<F> FieldTransform for F
+where
+    F: Fn(&str, &mut serde_json::Value) + Send + Sync,
+{
+    fn transform(&self, name: &str, value: &mut serde_json::Value) {
+        self(name, value)
+    }
+}
+
+/// Built-in `FieldTransform` that replaces the values of fields with the given names with
+/// `"[REDACTED]"`. Nested fields (e.g. `labels.api_token`) are matched by either their full name or
+/// their nested key.
+#[derive(Clone, Debug, Default)]
+pub struct Redact {
+    fields: Vec<String>,
+}
+
+impl Redact {
+    /// Creates a transform redacting the given field names
+    pub fn fields(fields: &[&str]) -> Self {
+        Self {
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+        }
+    }
+}
+
+impl FieldTransform for Redact {
+    fn transform(&self, name: &str, value: &mut serde_json::Value) {
+        let nested_name = name.split_once('.').map(|(_, nested_name)| nested_name);
+
+        if self
+            .fields
+            .iter()
+            .any(|field| field == name || Some(field.as_str()) == nested_name)
+        {
+            *value = serde_json::Value::from(REDACTED);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+/// Marker replacing `valuable` containers nested beyond the maximum depth
+const TRUNCATED: &str = "__truncated";
+
+/// Converts a `valuable` Value into JSON, replacing lists, maps, structs, enums and tuples nested
+/// more than `max_depth` levels deep with `{"__truncated": true}`. Output otherwise matches
+/// `valuable_serde`, but recursion is bounded by `max_depth` rather than by the structure itself.
+pub(crate) fn depth_limited_value(
+    value: valuable::Value<'_>,
+    max_depth: usize,
+) -> serde_json::Value {
+    DepthLimited::new(0, max_depth).convert(value)
+}
+
+/// `valuable` visitor collecting the children of a single container
+struct DepthLimited {
+    depth: usize,
+    max_depth: usize,
+    named: serde_json::Map<String, serde_json::Value>,
+    unnamed: Vec<serde_json::Value>,
+}
+
+impl DepthLimited {
+    fn new(depth: usize, max_depth: usize) -> Self {
+        Self {
+            depth,
+            max_depth,
+            named: serde_json::Map::new(),
+            unnamed: Vec::new(),
+        }
+    }
+
+    /// Converts a Value at this visitor's depth
+    fn convert(&self, value: valuable::Value<'_>) -> serde_json::Value {
+        use valuable::{Fields, Value};
+
+        let is_container = matches!(
+            value,
+            Value::Listable(_)
+                | Value::Mappable(_)
+                | Value::Structable(_)
+                | Value::Enumerable(_)
+                | Value::Tuplable(_)
+        );
+
+        if is_container && self.depth >= self.max_depth {
+            return serde_json::json!({ TRUNCATED: true });
+        }
+
+        let mut children = Self::new(self.depth + 1, self.max_depth);
+
+        match value {
+            Value::Bool(value) => serde_json::Value::from(value),
+            Value::Char(value) => serde_json::Value::from(value.to_string()),
+            Value::F32(value) => serde_json::Value::from(value),
+            Value::F64(value) => serde_json::Value::from(value),
+            Value::I8(value) => serde_json::Value::from(value),
+            Value::I16(value) => serde_json::Value::from(value),
+            Value::I32(value) => serde_json::Value::from(value),
+            Value::I64(value) => serde_json::Value::from(value),
+            Value::Isize(value) => serde_json::Value::from(value),
+            Value::U8(value) => serde_json::Value::from(value),
+            Value::U16(value) => serde_json::Value::from(value),
+            Value::U32(value) => serde_json::Value::from(value),
+            Value::U64(value) => serde_json::Value::from(value),
+            Value::Usize(value) => serde_json::Value::from(value),
+            // 128-bit integers are serialized as strings, as with fields recorded directly
+            Value::I128(value) => serde_json::Value::from(value.to_string()),
+            Value::U128(value) => serde_json::Value::from(value.to_string()),
+            Value::String(value) => serde_json::Value::from(value),
+            Value::Path(value) => serde_json::Value::from(value.display().to_string()),
+            Value::Error(value) => serde_json::Value::from(value.to_string()),
+            Value::Listable(listable) => {
+                listable.visit(&mut children);
+                serde_json::Value::Array(children.unnamed)
+            }
+            Value::Tuplable(tuplable) => {
+                tuplable.visit(&mut children);
+                serde_json::Value::Array(children.unnamed)
+            }
+            Value::Mappable(mappable) => {
+                mappable.visit(&mut children);
+                serde_json::Value::Object(children.named)
+            }
+            Value::Structable(structable) => {
+                structable.visit(&mut children);
+                children.into_fields(structable.definition().fields())
+            }
+            Value::Enumerable(enumerable) => {
+                let variant = enumerable.variant();
+
+                // unit variants are serialized as their name, and others as a single-key object
+                if let Fields::Unnamed(0) = variant.fields() {
+                    return serde_json::Value::from(variant.name());
+                }
+
+                enumerable.visit(&mut children);
+
+                let mut object = serde_json::Map::new();
+                object.insert(
+                    variant.name().to_owned(),
+                    children.into_fields(variant.fields()),
+                );
+                serde_json::Value::Object(object)
+            }
+            // units (including `None`) are null
+            _ => serde_json::Value::Null,
+        }
+    }
+
+    /// Returns the visited fields of a struct or enum variant, unwrapping newtypes
+    fn into_fields(mut self, fields: &valuable::Fields<'_>) -> serde_json::Value {
+        match fields {
+            valuable::Fields::Named(_) => serde_json::Value::Object(self.named),
+            valuable::Fields::Unnamed(1) if self.unnamed.len() == 1 => {
+                self.unnamed.pop().unwrap_or_default()
+            }
+            valuable::Fields::Unnamed(_) => serde_json::Value::Array(self.unnamed),
+        }
+    }
+}
+
+impl valuable::Visit for DepthLimited {
+    fn visit_value(&mut self, value: valuable::Value<'_>) {
+        let value = self.convert(value);
+        self.unnamed.push(value);
+    }
+
+    fn visit_named_fields(&mut self, named_values: &valuable::NamedValues<'_>) {
+        for (field, value) in named_values {
+            let value = self.convert(*value);
+            self.named.insert(field.name().to_owned(), value);
+        }
+    }
+
+    fn visit_unnamed_fields(&mut self, values: &[valuable::Value<'_>]) {
+        for value in values {
+            let value = self.convert(*value);
+            self.unnamed.push(value);
+        }
+    }
+
+    fn visit_entry(&mut self, key: valuable::Value<'_>, value: valuable::Value<'_>) {
+        // JSON keys are always strings, so non-string keys are serialized as compact JSON
+        let key = match self.convert(key) {
+            serde_json::Value::String(key) => key,
+            key => key.to_string(),
+        };
+        let value = self.convert(value);
+
+        self.named.insert(key, value);
+    }
+}
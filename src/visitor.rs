@@ -1,148 +1,726 @@
-use crate::google::LogSeverity;
+use crate::{event_formatter::EventFormatter, google::LogSeverity, log_entry::LogEntry};
 use inflector::Inflector;
-use serde::ser::SerializeMap;
-use std::{collections::BTreeMap, fmt};
-use tracing_core::Field;
-use tracing_subscriber::field::{Visit, VisitOutput};
+use std::{borrow::Cow, collections::BTreeMap, fmt};
+use tracing_core::{field::FieldSet, Field};
+use tracing_subscriber::field::Visit;
+
+/// Replacement value for redacted fields
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Maximum number of labels per entry accepted by Cloud Logging
+const MAX_LABELS: usize = 64;
+
+/// Maximum length of label keys accepted by Cloud Logging, in bytes
+const MAX_LABEL_KEY_BYTES: usize = 512;
+
+/// Maximum length of label values accepted by Cloud Logging, in bytes
+const MAX_LABEL_VALUE_BYTES: usize = 64 * 1024;
+
+/// Truncates a string to at most `max_bytes` on a character boundary, returning whether or not it
+/// was truncated
+fn truncate(value: &mut String, max_bytes: usize) -> bool {
+    if value.len() <= max_bytes {
+        return false;
+    }
+
+    let mut index = max_bytes;
+
+    while !value.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    value.truncate(index);
+    true
+}
+
+/// Enforces Cloud Logging's limits on labels, truncating oversized keys and values and dropping
+/// labels (in key order) beyond the maximum count, returning the number of labels affected
+fn limit_labels(labels: BTreeMap<String, String>) -> (BTreeMap<String, String>, usize) {
+    let mut truncated = 0;
+    let mut limited = BTreeMap::new();
+
+    for (mut key, mut value) in labels {
+        let key_truncated = truncate(&mut key, MAX_LABEL_KEY_BYTES);
+        let value_truncated = truncate(&mut value, MAX_LABEL_VALUE_BYTES);
+
+        if limited.len() >= MAX_LABELS {
+            truncated += 1;
+        } else {
+            truncated += usize::from(key_truncated || value_truncated);
+            limited.insert(key, value);
+        }
+    }
+
+    (limited, truncated)
+}
+
+/// Largest integer that can be represented exactly by an f64
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Converts a JSON value into a string, as required for special fields like labels. Integers are
+/// written without decimals (even when recorded as whole floats, e.g. `1.0` becomes `1`), and
+/// other floats in their shortest round-trippable form (e.g. `0.25`).
+fn stringify(value: serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value,
+        serde_json::Value::Number(number) => match number.as_f64() {
+            Some(float)
+                if number.is_f64() && float.fract() == 0.0 && float.abs() <= MAX_SAFE_INTEGER =>
+            {
+                (float as i64).to_string()
+            }
+            _ => number.to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+/// Canonical names of gRPC status codes, indexed by code
+const GRPC_STATUS_NAMES: [&str; 17] = [
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+/// Stringifies a gRPC status for a label, using the canonical name of numeric status codes
+fn grpc_status(value: serde_json::Value) -> String {
+    match value
+        .as_u64()
+        .and_then(|code| GRPC_STATUS_NAMES.get(code as usize))
+    {
+        Some(name) => (*name).to_owned(),
+        None => stringify(value),
+    }
+}
+
+/// Reads a `labels` field as a map of labels, whether structured (e.g. through `valuable`) or
+/// recorded as a JSON object string (e.g. the `Debug` output of a map of strings), returning
+/// the original value otherwise
+fn label_map(
+    value: serde_json::Value,
+) -> Result<serde_json::Map<String, serde_json::Value>, serde_json::Value> {
+    match value {
+        serde_json::Value::Object(labels) => Ok(labels),
+        serde_json::Value::String(text) => match serde_json::from_str(&text) {
+            Ok(serde_json::Value::Object(labels)) => Ok(labels),
+            _ => Err(serde_json::Value::String(text)),
+        },
+        value => Err(value),
+    }
+}
+
+/// Keys of the IP addresses of HTTP requests, formatted according to the `IpFormat` of the Layer
+const HTTP_REQUEST_IP_KEYS: [&str; 2] = ["remoteIp", "serverIp"];
+
+/// Converts the `Debug` output of a `Result` into an `{"ok": ...}` or `{"err": ...}` object
+fn result_object(debug: &str) -> Option<serde_json::Value> {
+    let (key, inner) = if let Some(inner) = debug.strip_prefix("Ok(") {
+        ("ok", inner)
+    } else if let Some(inner) = debug.strip_prefix("Err(") {
+        ("err", inner)
+    } else {
+        return None;
+    };
+
+    let inner = inner.strip_suffix(')')?;
+    let value = serde_json::from_str(inner).unwrap_or_else(|_| serde_json::Value::from(inner));
+
+    Some(serde_json::json!({ key: value }))
+}
 
 /// Visitor for Stackdriver events that formats custom fields
-pub(crate) struct Visitor<'a, S>
-where
-    S: SerializeMap,
-{
-    values: BTreeMap<&'a str, serde_json::Value>,
+pub(crate) struct Visitor<'a> {
+    values: BTreeMap<Cow<'a, str>, serde_json::Value>,
+    // the order in which each field was first recorded, for `FieldOrder::InsertionOrder`
+    positions: BTreeMap<Cow<'a, str>, usize>,
+    payload: BTreeMap<String, serde_json::Value>,
+    labels: BTreeMap<String, String>,
+    message_field: Option<Field>,
+    insert_id: Option<String>,
+    trace_id: Option<String>,
+    min_severity: LogSeverity,
     severity: LogSeverity,
-    serializer: S,
+    // the entry being built, including any special fields known before the Event is visited
+    entry: LogEntry,
+    config: &'a EventFormatter,
 }
 
-impl<'a, S> Visitor<'a, S>
-where
-    S: SerializeMap,
-{
-    /// Returns a new default visitor using the provided writer
-    pub(crate) fn new(severity: LogSeverity, serializer: S) -> Self {
+impl<'a> Visitor<'a> {
+    /// Returns a new visitor that completes the provided entry
+    pub(crate) fn new(severity: LogSeverity, entry: LogEntry, config: &'a EventFormatter) -> Self {
+        // the version label and dynamic labels are the lowest-precedence labels, overridden by
+        // spans and Events alike
+        let labels = config
+            .version_label
+            .iter()
+            .map(|version| ("version".to_owned(), version.clone()))
+            .chain(config.dynamic_labels.iter().filter_map(|label| {
+                (label.value)().map(|value| (config.field_casing.apply(&label.key), value))
+            }))
+            .collect();
+
         Self {
             values: BTreeMap::new(),
+            positions: BTreeMap::new(),
+            payload: BTreeMap::new(),
+            labels,
+            message_field: None,
+            insert_id: None,
+            trace_id: None,
+            min_severity: LogSeverity::Default,
             severity,
-            serializer,
+            entry,
+            config,
         }
     }
+
+    /// Configures the field used as the Event message. When an Event has both an explicit
+    /// `message` field and a formatted message (e.g. `info!(message = "explicit", "formatted")`),
+    /// tracing declares the formatted message first, and that first-declared `message` field wins.
+    pub(crate) fn set_message_field(&mut self, fields: &FieldSet) {
+        self.message_field = fields.iter().find(|field| field.name() == "message");
+    }
+
+    /// Raises the severity floor of the Event (e.g. from the spans enclosing it), which takes
+    /// precedence over both the Event's level and any less severe `severity` field
+    pub(crate) fn raise_min_severity(&mut self, min_severity: LogSeverity) {
+        self.min_severity = self.min_severity.max(min_severity);
+    }
+
+    /// Records the value of an Event field, ignoring shadowed `message` fields
+    fn insert_field(&mut self, field: &Field, value: serde_json::Value) {
+        if field.name() == "message"
+            && self
+                .message_field
+                .as_ref()
+                .is_some_and(|message_field| message_field != field)
+        {
+            return;
+        }
+
+        self.insert_value(Cow::Borrowed(field.name()), value);
+    }
+
+    /// Records the value of a field, along with the position at which it was first recorded
+    fn insert_value(&mut self, key: Cow<'a, str>, value: serde_json::Value) {
+        let position = self.positions.len();
+        self.positions.entry(key.clone()).or_insert(position);
+        self.values.insert(key, value);
+    }
+
+    /// Truncates a string beyond the configured maximum length (in characters) with an ellipsis,
+    /// marking the entry with a `__truncated` field
+    fn limit_string(&mut self, mut value: String) -> String {
+        if let Some((index, _)) = self
+            .config
+            .max_field_string_len
+            .and_then(|max_len| value.char_indices().nth(max_len))
+        {
+            value.truncate(index);
+            value.push('…');
+            self.payload
+                .insert("__truncated".to_owned(), serde_json::Value::from(true));
+        }
+
+        value
+    }
+
+    /// Adds a field from outside of the Event (e.g. a flattened span field) with a dynamic key,
+    /// which is handled like any other Event field and overridden by Event fields of the same key
+    pub(crate) fn insert_field_value(&mut self, key: String, value: serde_json::Value) {
+        self.insert_value(Cow::Owned(key), value);
+    }
+
+    /// Adds a label inherited from outside of the Event (e.g. from its spans), which is
+    /// overridden by any label of the same key recorded on the Event itself
+    pub(crate) fn insert_label(&mut self, key: &str, value: serde_json::Value) {
+        self.labels.insert(
+            self.config.field_casing.apply(key),
+            self.stringify_label(value),
+        );
+    }
+
+    /// Stringifies a label value, formatting booleans as configured
+    fn stringify_label(&self, value: serde_json::Value) -> String {
+        stringify(self.config.bool_label_format.apply(value))
+    }
+
+    /// Sets an insert ID inherited from outside of the Event (e.g. from its spans), which is
+    /// overridden by any `insert_id` recorded on the Event itself
+    pub(crate) fn set_insert_id(&mut self, insert_id: serde_json::Value) {
+        self.insert_id = Some(stringify(insert_id));
+    }
+
+    /// Sets a trace ID inherited from outside of the Event (e.g. from its spans), which is
+    /// overridden by any `trace_id` recorded on the Event itself
+    pub(crate) fn set_trace_id(&mut self, trace_id: serde_json::Value) {
+        self.trace_id = Some(stringify(trace_id));
+    }
+
+    /// Inherits the labels, HTTP request fields, insert ID, trace ID and severity floor recorded
+    /// on a span. Spans are inherited from the root inwards, with inner spans (and then the Event
+    /// itself) taking precedence for everything but severity floors, where the most severe floor
+    /// wins.
+    pub(crate) fn inherit_span_fields(
+        &mut self,
+        fields: serde_json::Map<String, serde_json::Value>,
+    ) {
+        for (key, value) in fields {
+            if let Some(label_key) = key.strip_prefix("labels.") {
+                self.insert_label(label_key, value);
+            } else if key.starts_with("http_request.") {
+                self.insert_field_value(key, value);
+            } else if matches!(key.as_str(), "insert_id" | "insertId") {
+                self.set_insert_id(value);
+            } else if key == "trace_id" {
+                self.set_trace_id(value);
+            } else if key == "min_severity" || key == "severity" {
+                self.raise_min_severity(LogSeverity::from(value));
+            }
+        }
+    }
+
+    /// Finds any field that would be emitted with a "severity" key (e.g. `Severity` when
+    /// camelCasing), which overrides the level-derived severity
+    fn severity_key(&self) -> Option<Cow<'a, str>> {
+        self.values
+            .keys()
+            .find(|key| self.config.field_casing.apply(key) == "severity")
+            .cloned()
+    }
+
+    /// Resolves the severity the entry will be emitted with from its level, any `severity` field,
+    /// the configured default severity and the severity floor of its spans
+    pub(crate) fn severity(&self) -> LogSeverity {
+        let severity = self
+            .severity_key()
+            .and_then(|key| self.values.get(&key).cloned())
+            .map(LogSeverity::from)
+            .unwrap_or(self.severity);
+
+        match severity {
+            LogSeverity::Default => self.config.default_severity,
+            severity => severity,
+        }
+        .max(self.min_severity)
+    }
+
+    /// Adds a pre-serialized value to the payload of the entry, bypassing field handling and casing
+    pub(crate) fn insert(&mut self, key: impl Into<String>, value: serde_json::Value) {
+        self.payload.insert(key.into(), value);
+    }
 }
 
-impl<'a, S> VisitOutput<fmt::Result> for Visitor<'a, S>
-where
-    S: SerializeMap,
-{
-    fn finish(mut self) -> fmt::Result {
-        let inner = || {
-            let severity = self
-                .values
-                .remove("severity")
-                .map(LogSeverity::from)
-                .unwrap_or(self.severity);
-
-            self.serializer.serialize_entry("severity", &severity)?;
-
-            let mut http_request = BTreeMap::new();
-            let mut labels = BTreeMap::new();
-
-            for (key, value) in self.values {
-                let mut key_segments = key.splitn(2, '.');
-
-                match (key_segments.next(), key_segments.next()) {
-                    (Some("http_request"), Some(request_key)) => {
-                        http_request.insert(request_key.to_camel_case(), value);
+impl<'a> Visitor<'a> {
+    /// Completes the entry with the visited fields
+    pub(crate) fn finish(mut self) -> LogEntry {
+        let severity = self.severity();
+
+        // only one severity is ever emitted, so a field that overrode it is dropped
+        if let Some(key) = self.severity_key() {
+            self.values.remove(&key);
+        }
+
+        self.entry.severity = severity;
+
+        if let crate::MessageSource::Field(key) = &self.config.message_source {
+            if let Some(message) = self.values.remove(key.as_str()) {
+                self.values.insert(Cow::Borrowed("message"), message);
+            }
+        }
+
+        let mut http_request = BTreeMap::new();
+        let mut labels = self.labels;
+        let mut payload = self.payload;
+        let mut insert_id = None;
+        let mut trace_id = None;
+        let mut request_id = None;
+        let mut handled = BTreeMap::<&str, serde_json::Map<String, serde_json::Value>>::new();
+        let mut raw_json = serde_json::Map::new();
+        let mut raw_json_error = None;
+        // positions at which the Event fields behind each payload key were first recorded
+        let mut ranks = BTreeMap::<String, Option<usize>>::new();
+
+        for (key, value) in self.values {
+            let key = key.as_ref();
+            let mut value = if self.config.is_redacted(key) {
+                serde_json::Value::from(REDACTED)
+            } else {
+                value
+            };
+
+            for transform in &self.config.transforms {
+                transform.transform(key, &mut value);
+            }
+
+            // request IDs are copied into labels and insert IDs, in addition to the payload
+            if self.config.request_id_field.as_deref() == Some(key) {
+                let value = stringify(value.clone());
+                labels.insert("request_id".to_owned(), value.clone());
+                request_id = Some(value);
+            }
+
+            // custom field handlers take precedence over the built-in special fields
+            if let Some((handler, nested_key)) =
+                self.config.field_handlers.iter().find_map(|handler| {
+                    handler
+                        .nested_key(key)
+                        .map(|nested_key| (handler, nested_key))
+                })
+            {
+                let (nested_key, value) = (handler.handler)(nested_key, value);
+
+                handled
+                    .entry(handler.key.as_str())
+                    .or_default()
+                    .insert(nested_key, value);
+
+                continue;
+            }
+
+            // maps of labels (e.g. from `header_labels`) are merged into the labels, with
+            // individual `labels.`-prefixed fields (which are visited later) taking precedence
+            let value = if key == "labels" {
+                match label_map(value) {
+                    Ok(label_map) => {
+                        for (label_key, value) in label_map {
+                            labels.insert(
+                                self.config.field_casing.apply(&label_key),
+                                stringify(self.config.bool_label_format.apply(value)),
+                            );
+                        }
+
+                        continue;
                     }
-                    (Some("labels"), Some(label_key)) => {
-                        let value = match value {
-                            serde_json::Value::String(value) => value,
-                            _ => value.to_string(),
-                        };
+                    Err(value) => value,
+                }
+            } else {
+                value
+            };
 
-                        labels.insert(label_key.to_camel_case(), value);
+            // IP addresses of structured HTTP requests (e.g. `HttpRequest`) are formatted as
+            // configured, like those of `http_request.`-prefixed fields
+            let value = match (key, value) {
+                ("http_request", serde_json::Value::Object(mut request)) => {
+                    for ip_key in HTTP_REQUEST_IP_KEYS {
+                        if let Some(ip) = request.get_mut(ip_key) {
+                            *ip = self.config.ip_format.apply(ip.take());
+                        }
                     }
-                    (Some("insert_id"), None) => {
-                        let value = match value {
-                            serde_json::Value::String(value) => value,
-                            _ => value.to_string(),
-                        };
-
-                        self.serializer
-                            .serialize_entry("logging.googleapis.com/insertId", &value)?;
+
+                    serde_json::Value::Object(request)
+                }
+                (_, value) => value,
+            };
+
+            let mut key_segments = key.splitn(2, '.');
+
+            match (key_segments.next(), key_segments.next()) {
+                (Some("http_request"), Some(request_key)) => {
+                    let request_key = request_key.to_camel_case();
+                    let value = if HTTP_REQUEST_IP_KEYS.contains(&request_key.as_str()) {
+                        self.config.ip_format.apply(value)
+                    } else {
+                        value
+                    };
+
+                    http_request.insert(request_key, value);
+                }
+                (Some("labels"), Some(label_key)) => {
+                    labels.insert(
+                        self.config.field_casing.apply(label_key),
+                        stringify(self.config.bool_label_format.apply(value)),
+                    );
+                }
+                (Some("grpc"), Some("method")) if self.config.grpc_labels => {
+                    labels.insert(
+                        self.config.field_casing.apply("grpc_method"),
+                        stringify(value),
+                    );
+                }
+                (Some("grpc"), Some("status")) if self.config.grpc_labels => {
+                    labels.insert(
+                        self.config.field_casing.apply("grpc_status"),
+                        grpc_status(value),
+                    );
+                }
+                // camelCased insert IDs are recognized too, as they match the Google key
+                (Some("insert_id" | "insertId"), None) => {
+                    insert_id = Some(stringify(value));
+                }
+                // trace IDs can only be formatted as traces with a project ID
+                (Some("trace_id"), None)
+                    if self.config.project_id.is_some() && self.entry.trace.is_none() =>
+                {
+                    trace_id = Some(stringify(value));
+                }
+                (Some("trace_sampled"), None) => {
+                    self.entry.trace_sampled |= match value {
+                        serde_json::Value::Bool(value) => value,
+                        serde_json::Value::String(value) => value == "true" || value == "1",
+                        serde_json::Value::Number(value) => value.as_u64() == Some(1),
+                        _ => false,
+                    };
+                }
+                // pre-serialized objects are merged as-is, and dropped with a `rawJsonError` if
+                // they aren't objects
+                (Some("__raw_json"), None) => {
+                    let parsed = match &value {
+                        serde_json::Value::String(text) => {
+                            serde_json::from_str(text).map_err(|error| error.to_string())
+                        }
+                        _ => Err("expected a string of JSON".to_owned()),
+                    };
+
+                    match parsed {
+                        Ok(serde_json::Value::Object(mut fields)) => {
+                            // a `labels` object is merged into the labels like a `labels`
+                            // field, though Event labels take precedence
+                            if let Some(value) = fields.remove("labels") {
+                                match label_map(value) {
+                                    Ok(label_map) => {
+                                        let config = self.config;
+
+                                        for (label_key, value) in label_map {
+                                            labels
+                                                .entry(config.field_casing.apply(&label_key))
+                                                .or_insert_with(|| {
+                                                    stringify(config.bool_label_format.apply(value))
+                                                });
+                                        }
+                                    }
+                                    Err(value) => {
+                                        fields.insert("labels".to_owned(), value);
+                                    }
+                                }
+                            }
+
+                            raw_json.extend(fields)
+                        }
+                        Ok(_) => raw_json_error = Some("expected a JSON object".to_owned()),
+                        Err(error) => raw_json_error = Some(error),
                     }
-                    (Some(key), None) => self
-                        .serializer
-                        .serialize_entry(&key.to_camel_case(), &value)?,
-                    _ => self
-                        .serializer
-                        .serialize_entry(&key.to_camel_case(), &value)?,
                 }
-            }
+                // backtraces (e.g. `backtrace = %backtrace`) are renamed to the stack trace
+                // field that Error Reporting recognizes
+                (Some("backtrace"), None) => {
+                    let position = self.positions.get(key).copied();
+                    let key = self.config.field_casing.apply("stack_trace");
 
-            if !http_request.is_empty() {
-                self.serializer
-                    .serialize_entry("httpRequest", &http_request)?;
+                    ranks.insert(key.clone(), position);
+                    payload.insert(key, value);
+                }
+                // typed payloads are recognized by their exact @type key, so it's never re-cased
+                (Some("@type" | "type_url"), None) => {
+                    payload.insert("@type".to_owned(), value);
+                }
+                _ => {
+                    let value = if self.config.deep_casing {
+                        self.config.field_casing.apply_deep(value)
+                    } else {
+                        value
+                    };
+                    let value = self.config.float_format.apply(value);
+                    let position = self.positions.get(key).copied();
+                    let key = self.config.field_casing.apply(key);
+
+                    ranks.insert(key.clone(), position);
+                    payload.insert(key, value);
+                }
             }
+        }
+
+        for (key, fields) in handled {
+            payload.insert(key.to_owned(), serde_json::Value::Object(fields));
+        }
+
+        if let Some(error) = raw_json_error {
+            payload.insert(
+                self.config.field_casing.apply("raw_json_error"),
+                serde_json::Value::from(error),
+            );
+        }
+
+        // Event fields take precedence over pre-serialized fields with the same key
+        for (key, value) in raw_json {
+            payload.entry(key).or_insert(value);
+        }
+
+        let labels = match self.config.labels_mode {
+            crate::LabelsMode::Google => {
+                // entries that exceed the label limits are rejected entirely, so labels are
+                // limited up-front, with the number of truncated or dropped labels recorded
+                // in the payload
+                let (labels, labels_truncated) = limit_labels(labels);
+
+                if labels_truncated > 0 {
+                    payload.insert(
+                        self.config.field_casing.apply("labels_truncated"),
+                        serde_json::Value::from(labels_truncated),
+                    );
+                }
 
-            if !labels.is_empty() {
-                self.serializer
-                    .serialize_entry("logging.googleapis.com/labels", &labels)?;
+                labels
             }
+            crate::LabelsMode::RootFields => {
+                for (key, value) in labels {
+                    payload.entry(key).or_insert(serde_json::Value::from(value));
+                }
 
-            self.serializer.end()
+                BTreeMap::new()
+            }
         };
 
-        if inner().is_err() {
-            Err(fmt::Error)
-        } else {
-            Ok(())
+        self.entry.insert_id = insert_id.or(request_id).or(self.insert_id);
+
+        if let (Some(project_id), Some(trace_id), false) = (
+            &self.config.project_id,
+            trace_id.or(self.trace_id),
+            self.entry.trace.is_some(),
+        ) {
+            let (trace_id, span_id, sampled) = if self.config.cloud_trace_context {
+                crate::google::parse_cloud_trace_context(&trace_id)
+            } else {
+                (trace_id.as_str(), None, false)
+            };
+
+            self.entry.trace = Some(crate::google::format_trace(
+                self.config.trace_template.as_deref(),
+                project_id,
+                trace_id,
+            ));
+            self.entry.span_id = self.entry.span_id.take().or(span_id);
+            self.entry.trace_sampled |= sampled;
+        }
+
+        let mut payload: Vec<_> = payload.into_iter().collect();
+
+        // payload fields are ordered alphabetically by key, unless ordered by insertion, where
+        // fields that weren't recorded on the Event (e.g. pre-serialized fields) follow those
+        // that were, alphabetically
+        if self.config.field_order == crate::FieldOrder::InsertionOrder {
+            payload.sort_by_key(|(key, _)| ranks.get(key).copied().flatten().unwrap_or(usize::MAX));
+        }
+
+        // fields can't clobber the entry's own timestamp, so they're renamed instead
+        if !self.entry.json_payload {
+            for (key, _) in &mut payload {
+                if key == "time" || key == "timestamp" {
+                    *key = self.config.field_casing.apply(&format!("{key}_field"));
+                }
+            }
         }
+
+        self.entry.fields = payload;
+        self.entry.http_request = http_request.into_iter().collect();
+        self.entry.labels = labels;
+        self.entry
     }
 }
 
-impl<'a, S> Visit for Visitor<'a, S>
-where
-    S: SerializeMap,
-{
+impl<'a> Visit for Visitor<'a> {
     fn record_i64(&mut self, field: &Field, value: i64) {
-        self.values
-            .insert(field.name(), serde_json::Value::from(value));
+        self.insert_field(field, serde_json::Value::from(value));
     }
 
     fn record_u64(&mut self, field: &Field, value: u64) {
-        self.values
-            .insert(field.name(), serde_json::Value::from(value));
+        self.insert_field(field, serde_json::Value::from(value));
+    }
+
+    // non-finite floats have no JSON representation, so they're serialized as strings
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        let value = serde_json::Number::from_f64(value)
+            .map(serde_json::Value::Number)
+            .unwrap_or_else(|| serde_json::Value::from(value.to_string()));
+
+        self.insert_field(field, value);
+    }
+
+    // 128-bit integers can't be represented losslessly by most JSON parsers, so they're
+    // serialized as strings
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.insert_field(field, serde_json::Value::from(value.to_string()));
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.insert_field(field, serde_json::Value::from(value.to_string()));
     }
 
     fn record_bool(&mut self, field: &Field, value: bool) {
-        self.values
-            .insert(field.name(), serde_json::Value::from(value));
+        self.insert_field(field, serde_json::Value::from(value));
     }
 
     fn record_str(&mut self, field: &Field, value: &str) {
-        self.values
-            .insert(field.name(), serde_json::Value::from(value));
+        // configured fields holding pre-serialized JSON are embedded as structured values
+        if self
+            .config
+            .json_string_fields
+            .iter()
+            .any(|json_field| json_field == field.name())
+        {
+            if let Ok(value) = serde_json::from_str(value) {
+                return self.insert_field(field, value);
+            }
+        }
+
+        let value = self.limit_string(value.to_owned());
+        self.insert_field(field, serde_json::Value::from(value));
     }
 
     fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
-        self.values.insert(
-            field.name(),
-            serde_json::Value::from(format!("{:?}", value)),
-        );
+        let value = format!("{:?}", value);
+
+        let value = self
+            .config
+            .result_objects
+            .then(|| result_object(&value))
+            .flatten()
+            .unwrap_or_else(|| serde_json::Value::from(self.limit_string(value)));
+
+        self.insert_field(field, value);
     }
 
     #[cfg(all(tracing_unstable, feature = "valuable"))]
     fn record_value(&mut self, field: &Field, value: valuable::Value<'_>) {
-        let value = serde_json::to_value(valuable_serde::Serializable::new(value)).unwrap();
+        let value = match value {
+            valuable::Value::Structable(structable) => {
+                if let Some(crate::LogDuration(duration)) =
+                    crate::LogDuration::from_structable(structable)
+                {
+                    Some(self.config.duration_format.apply(duration))
+                } else if crate::LogInstant::is_structable(structable) {
+                    Some(serde_json::Value::Null)
+                } else {
+                    crate::LogTimestamp::from_structable(structable)
+                        .map(|timestamp| serde_json::Value::from(timestamp.format()))
+                }
+            }
+            _ => None,
+        }
+        .unwrap_or_else(|| match self.config.max_value_depth {
+            Some(max_depth) => crate::value_depth::depth_limited_value(value, max_depth),
+            None => serde_json::to_value(valuable_serde::Serializable::new(value)).unwrap(),
+        });
 
-        self.values.insert(field.name(), value);
+        self.insert_field(field, value);
     }
 }
 
-impl<'a, S> fmt::Debug for Visitor<'a, S>
-where
-    S: SerializeMap,
-{
+impl<'a> fmt::Debug for Visitor<'a> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter
             .debug_struct("Visitor")
@@ -1,34 +1,69 @@
+use crate::google::LogSeverity;
 use std::{
+    borrow::Cow,
     fmt::{Formatter, Write},
     io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
+use tracing_core::Metadata;
+use tracing_subscriber::fmt::{writer::EitherWriter, MakeWriter};
 
 /// Utility newtype for converting between fmt::Write and io::Write
 // https://docs.rs/tracing-subscriber/latest/src/tracing_subscriber/fmt/writer.rs.html
 pub(crate) struct WriteAdaptor<'a> {
     fmt_write: &'a mut dyn Write,
+    // leading bytes of a multibyte character split across writes, completed by the next write
+    pending: Vec<u8>,
 }
 
 impl<'a> WriteAdaptor<'a> {
     pub(crate) fn new(fmt_write: &'a mut dyn Write) -> Self {
-        Self { fmt_write }
+        Self {
+            fmt_write,
+            pending: Vec::new(),
+        }
     }
 }
 
 impl<'a> io::Write for WriteAdaptor<'a> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let s =
-            std::str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let bytes = if self.pending.is_empty() {
+            Cow::Borrowed(buf)
+        } else {
+            let mut bytes = std::mem::take(&mut self.pending);
+            bytes.extend_from_slice(buf);
+            Cow::Owned(bytes)
+        };
 
-        self.fmt_write
-            .write_str(s)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let s = match std::str::from_utf8(&bytes) {
+            Ok(s) => s,
+            // an incomplete character at the end is kept until the rest of it is written
+            Err(error) if error.error_len().is_none() => {
+                let (complete, incomplete) = bytes.split_at(error.valid_up_to());
+                self.pending = incomplete.to_vec();
+                std::str::from_utf8(complete).expect("Prefix was validated as UTF-8")
+            }
+            Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+        };
 
-        Ok(s.as_bytes().len())
+        self.fmt_write.write_str(s).map_err(io::Error::other)?;
+
+        Ok(buf.len())
     }
 
+    // a character that's still incomplete once writing is done was truncated
     fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+        if self.pending.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Truncated multibyte character",
+            ))
+        }
     }
 }
 
@@ -37,3 +72,395 @@ impl<'a> std::fmt::Debug for WriteAdaptor<'a> {
         formatter.pad("WriteAdaptor { .. }")
     }
 }
+
+/// JSON formatter that escapes all non-ASCII characters as `\uXXXX` sequences, for consumers that
+/// only accept ASCII output
+pub(crate) struct AsciiFormatter;
+
+impl serde_json::ser::Formatter for AsciiFormatter {
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let mut start = 0;
+
+        for (index, character) in fragment.char_indices() {
+            if !character.is_ascii() {
+                writer.write_all(&fragment.as_bytes()[start..index])?;
+
+                // characters outside of the Basic Multilingual Plane are escaped as surrogate pairs
+                for code_unit in character.encode_utf16(&mut [0; 2]) {
+                    write!(writer, "\\u{code_unit:04x}")?;
+                }
+
+                start = index + character.len_utf8();
+            }
+        }
+
+        writer.write_all(&fragment.as_bytes()[start..])
+    }
+}
+
+/// JSON formatter that colorizes the value of the top-level `severity` field with ANSI escape
+/// codes for terminal output, leaving every other key and value as-is. Only methods that the
+/// other formatters of this crate override are delegated to the wrapped formatter.
+pub(crate) struct SeverityColorFormatter<F> {
+    formatter: F,
+    // nesting depth of the object being written, where the entry itself is at depth 1
+    depth: usize,
+    // the top-level key being written, if any
+    key: Option<String>,
+    // whether or not the value being written belongs to the top-level `severity` key
+    severity_value: bool,
+    // the severity string being written, buffered until it's complete to pick its color
+    severity: Option<String>,
+}
+
+impl<F> SeverityColorFormatter<F> {
+    pub(crate) fn new(formatter: F) -> Self {
+        Self {
+            formatter,
+            depth: 0,
+            key: None,
+            severity_value: false,
+            severity: None,
+        }
+    }
+}
+
+impl<F> serde_json::ser::Formatter for SeverityColorFormatter<F>
+where
+    F: serde_json::ser::Formatter,
+{
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.depth += 1;
+        self.formatter.begin_object(writer)
+    }
+
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.depth -= 1;
+        self.formatter.end_object(writer)
+    }
+
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if self.depth == 1 {
+            self.key = Some(String::new());
+        }
+
+        self.formatter.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.severity_value = self.key.take().as_deref() == Some("severity");
+        self.formatter.begin_object_value(writer)
+    }
+
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.severity_value = false;
+        self.formatter.end_object_value(writer)
+    }
+
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if self.severity_value {
+            self.severity = Some(String::new());
+            return Ok(());
+        }
+
+        self.formatter.begin_string(writer)
+    }
+
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        let Some(severity) = self.severity.take() else {
+            return self.formatter.end_string(writer);
+        };
+
+        self.formatter.begin_string(writer)?;
+
+        match LogSeverity::from(serde_json::Value::from(severity.as_str())).ansi_color() {
+            Some(color) => {
+                write!(writer, "\x1b[{color}m")?;
+                self.formatter.write_string_fragment(writer, &severity)?;
+                write!(writer, "\x1b[0m")?;
+            }
+            None => self.formatter.write_string_fragment(writer, &severity)?,
+        }
+
+        self.formatter.end_string(writer)
+    }
+
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if let Some(severity) = &mut self.severity {
+            severity.push_str(fragment);
+            return Ok(());
+        }
+
+        if let Some(key) = &mut self.key {
+            key.push_str(fragment);
+        }
+
+        self.formatter.write_string_fragment(writer, fragment)
+    }
+}
+
+/// MakeWriter that routes entries to different writers by severity, e.g. for archiving `DEBUG`
+/// entries separately from everything else. Each route applies to severities at or above its own
+/// and below those of the next-most-severe route, with entries below every route falling back to
+/// a default writer. Severities are derived from the level of each Event, so `severity` field
+/// overrides don't affect routing.
+#[derive(Clone, Debug)]
+pub struct SeverityRouting<M, D> {
+    // sorted from most to least severe
+    routes: Vec<(LogSeverity, M)>,
+    default: D,
+}
+
+impl<M, D> SeverityRouting<M, D> {
+    /// Generate a router from a set of minimum severities and their writers, falling back to a
+    /// default writer
+    pub fn new(mut routes: Vec<(LogSeverity, M)>, default: D) -> Self {
+        routes.sort_by(|(severity, _), (other_severity, _)| other_severity.cmp(severity));
+
+        Self { routes, default }
+    }
+}
+
+impl<'a, M, D> MakeWriter<'a> for SeverityRouting<M, D>
+where
+    M: MakeWriter<'a>,
+    D: MakeWriter<'a>,
+{
+    type Writer = EitherWriter<M::Writer, D::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        EitherWriter::B(self.default.make_writer())
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        let severity = LogSeverity::from(meta.level());
+
+        match self
+            .routes
+            .iter()
+            .find(|(min_severity, _)| severity >= *min_severity)
+        {
+            Some((_, make_writer)) => EitherWriter::A(make_writer.make_writer_for(meta)),
+            None => EitherWriter::B(self.default.make_writer_for(meta)),
+        }
+    }
+}
+
+/// MakeWriter that counts failed writes of entries, e.g. for alerting on logging failures
+#[derive(Clone, Debug)]
+pub struct ErrorCounting<M> {
+    make_writer: M,
+    counter: Arc<AtomicU64>,
+}
+
+impl<M> ErrorCounting<M> {
+    /// Wrap a writer, incrementing the counter whenever a write fails
+    pub fn new(make_writer: M, counter: Arc<AtomicU64>) -> Self {
+        Self {
+            make_writer,
+            counter,
+        }
+    }
+}
+
+impl<'a, M> MakeWriter<'a> for ErrorCounting<M>
+where
+    M: MakeWriter<'a>,
+{
+    type Writer = ErrorCountingWriter<'a, M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ErrorCountingWriter {
+            writer: self.make_writer.make_writer(),
+            counter: &self.counter,
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        ErrorCountingWriter {
+            writer: self.make_writer.make_writer_for(meta),
+            counter: &self.counter,
+        }
+    }
+}
+
+/// Writer returned by `ErrorCounting`, which counts its failed writes
+#[derive(Debug)]
+pub struct ErrorCountingWriter<'a, W> {
+    writer: W,
+    counter: &'a AtomicU64,
+}
+
+impl<'a, W> ErrorCountingWriter<'a, W> {
+    fn count<T>(&self, result: io::Result<T>) -> io::Result<T> {
+        if result.is_err() {
+            self.counter.fetch_add(1, Ordering::Relaxed);
+        }
+
+        result
+    }
+}
+
+impl<'a, W> io::Write for ErrorCountingWriter<'a, W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.writer.write(buf);
+        self.count(result)
+    }
+
+    // entries are written with a single write_all, so each failed entry is counted once
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let result = self.writer.write_all(buf);
+        self.count(result)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.writer.flush();
+        self.count(result)
+    }
+}
+
+/// MakeWriter that flushes the writer of each entry right after the entry is written, if the
+/// Event's level is at or above a minimum severity. Severities are derived from the level of each
+/// Event, so `severity` field overrides don't affect flushing.
+#[derive(Clone, Debug)]
+pub struct FlushOnSeverity<M> {
+    make_writer: M,
+    flush_severity: LogSeverity,
+}
+
+impl<M> FlushOnSeverity<M> {
+    /// Wrap a writer, flushing it after writing entries at or above the given severity
+    pub fn new(make_writer: M, flush_severity: LogSeverity) -> Self {
+        Self {
+            make_writer,
+            flush_severity,
+        }
+    }
+}
+
+impl<'a, M> MakeWriter<'a> for FlushOnSeverity<M>
+where
+    M: MakeWriter<'a>,
+{
+    type Writer = FlushOnSeverityWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        FlushOnSeverityWriter {
+            writer: self.make_writer.make_writer(),
+            flush: false,
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        FlushOnSeverityWriter {
+            writer: self.make_writer.make_writer_for(meta),
+            flush: LogSeverity::from(meta.level()) >= self.flush_severity,
+        }
+    }
+}
+
+/// Writer returned by `FlushOnSeverity`, which flushes itself after writing a severe enough entry
+#[derive(Debug)]
+pub struct FlushOnSeverityWriter<W> {
+    writer: W,
+    flush: bool,
+}
+
+impl<W> io::Write for FlushOnSeverityWriter<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    // entries are written with a single write_all, which is empty for entries that were dropped
+    // (e.g. muted or sampled-out entries), so only entries that were emitted are flushed
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.writer.write_all(buf)?;
+
+        if self.flush && !buf.is_empty() {
+            self.writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WriteAdaptor;
+    use std::io::{self, Write as _};
+
+    #[test]
+    fn buffers_multibyte_characters_split_across_writes() {
+        let mut output = String::new();
+        let bytes = "héllo 👋".as_bytes();
+        let mut write_adaptor = WriteAdaptor::new(&mut output);
+
+        // split within both the two-byte "é" and the four-byte "👋"
+        write_adaptor.write_all(&bytes[..2]).unwrap();
+        write_adaptor.write_all(&bytes[2..9]).unwrap();
+        write_adaptor.write_all(&bytes[9..]).unwrap();
+
+        write_adaptor.flush().unwrap();
+
+        assert_eq!(output, "héllo 👋");
+    }
+
+    #[test]
+    fn rejects_truncated_trailing_characters() {
+        let mut output = String::new();
+        let bytes = "héllo 👋".as_bytes();
+        let mut write_adaptor = WriteAdaptor::new(&mut output);
+
+        // the last byte of the four-byte "👋" is never written
+        write_adaptor.write_all(&bytes[..bytes.len() - 1]).unwrap();
+
+        let error = write_adaptor.flush().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(output, "héllo ");
+    }
+
+    #[test]
+    fn rejects_invalid_utf8() {
+        let mut output = String::new();
+        let mut write_adaptor = WriteAdaptor::new(&mut output);
+
+        assert!(write_adaptor.write(&[b'a', 0xff, b'b']).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use helpers::run_with_tracing_layer_raw;
+
+mod helpers;
+
+fn emit_events() {
+    tracing::debug!("debug message");
+    tracing::info!("info message");
+    tracing::warn!("warning message");
+    tracing::error!("error message");
+}
+
+/// Removes ANSI SGR escape codes from colorized output
+fn strip_escape_codes(output: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = output;
+
+    while let Some(start) = rest.find('\x1b') {
+        stripped.push_str(&rest[..start]);
+        let end = rest[start..].find('m').expect("Unterminated escape code");
+        rest = &rest[start + end + 1..];
+    }
+
+    stripped.push_str(rest);
+    stripped
+}
+
+#[test]
+fn omits_escape_codes_by_default() {
+    let output = run_with_tracing_layer_raw(tracing_stackdriver::layer(), emit_events);
+    let output = String::from_utf8(output).expect("Invalid UTF-8 output");
+
+    assert_eq!(output.lines().count(), 4);
+    assert!(!output.contains('\x1b'));
+    assert!(!output.contains("\\u001b"));
+}
+
+#[test]
+fn colorizes_severity_values() {
+    let layer = tracing_stackdriver::layer().with_ansi(true);
+    let output = run_with_tracing_layer_raw(layer, emit_events);
+    let output = String::from_utf8(output).expect("Invalid UTF-8 output");
+    let lines: Vec<_> = output.lines().collect();
+
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].contains("\"severity\":\"\x1b[34mDEBUG\x1b[0m\""));
+    assert!(lines[1].contains("\"severity\":\"\x1b[32mINFO\x1b[0m\""));
+    assert!(lines[2].contains("\"severity\":\"\x1b[33mWARNING\x1b[0m\""));
+    assert!(lines[3].contains("\"severity\":\"\x1b[31mERROR\x1b[0m\""));
+
+    // each line holds exactly one colorized value, and is otherwise untouched
+    for line in lines {
+        assert_eq!(line.matches('\x1b').count(), 2);
+        assert!(line.starts_with('{'));
+
+        // raw escape codes are control characters, which JSON strings can't contain
+        assert!(serde_json::from_str::<serde_json::Value>(line).is_err());
+
+        serde_json::from_str::<serde_json::Value>(&strip_escape_codes(line))
+            .expect("Invalid JSON entry");
+    }
+}
+
+#[test]
+fn colorizes_overridden_severities() {
+    let layer = tracing_stackdriver::layer().with_ansi(true);
+    let output = run_with_tracing_layer_raw(layer, || {
+        tracing::info!(severity = "critical", labels.severity = "low", "overridden");
+    });
+    let output = String::from_utf8(output).expect("Invalid UTF-8 output");
+
+    assert!(output.contains("\"severity\":\"\x1b[1;31mCRITICAL\x1b[0m\""));
+
+    let entry: serde_json::Value =
+        serde_json::from_str(&strip_escape_codes(&output)).expect("Invalid JSON entry");
+
+    assert_eq!(entry["severity"], "CRITICAL");
+    assert_eq!(entry["logging.googleapis.com/labels"]["severity"], "low");
+}
+
+#[test]
+fn colorizes_with_ascii_escapes() {
+    let layer = tracing_stackdriver::layer()
+        .with_ansi(true)
+        .with_ascii_escapes(true);
+    let output = run_with_tracing_layer_raw(layer, || tracing::warn!("naïve"));
+    let output = String::from_utf8(output).expect("Invalid UTF-8 output");
+
+    assert!(output.contains("\"severity\":\"\x1b[33mWARNING\x1b[0m\""));
+    assert!(output.contains("na\\u00efve"));
+}
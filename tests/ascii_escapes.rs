@@ -0,0 +1,32 @@
+use helpers::run_with_tracing_layer_raw;
+use serde_json::{Map, Value};
+
+mod helpers;
+
+fn run(layer: tracing_stackdriver::Layer<tracing_subscriber::Registry>) -> String {
+    let output = run_with_tracing_layer_raw(layer, || {
+        tracing::info!(greeting = "héllo 👋", "hello!");
+    });
+
+    String::from_utf8(output).expect("Invalid UTF-8 output")
+}
+
+#[test]
+fn escapes_non_ascii_characters() {
+    let output = run(tracing_stackdriver::layer().with_ascii_escapes(true));
+
+    assert!(output.is_ascii());
+    assert!(output.contains(r#""greeting":"h\u00e9llo \ud83d\udc4b""#));
+
+    let event: Map<String, Value> =
+        serde_json::from_str(&output).expect("Error converting test buffer to JSON");
+
+    assert_eq!(event["greeting"], "héllo 👋");
+}
+
+#[test]
+fn keeps_non_ascii_characters_by_default() {
+    let output = run(tracing_stackdriver::layer());
+
+    assert!(output.contains(r#""greeting":"héllo 👋""#));
+}
@@ -0,0 +1,68 @@
+use helpers::run_with_tracing_layer;
+use serde_json::{Map, Value};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+mod helpers;
+
+fn run(layer: tracing_stackdriver::Layer<tracing_subscriber::Registry>) -> Map<String, Value> {
+    let mut events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        let span = tracing::info_span!("stackdriver_span", foo = "bar");
+        let _guard = span.enter();
+        tracing::info!(password = "hunter2", labels.foo = "bar", "hello!")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let mut event = events.pop().expect("No event heard");
+    event.remove("time");
+    event
+}
+
+#[test]
+fn clones_configured_layers() {
+    let layer = tracing_stackdriver::layer()
+        .with_source_location(false)
+        .with_structured_payload(true)
+        .with_redacted_fields(&["password"]);
+
+    let clone = layer.clone();
+    let clone_output = run(clone);
+    let output = run(layer);
+
+    assert_eq!(output, clone_output);
+    assert!(!output.contains_key("logging.googleapis.com/sourceLocation"));
+    assert_eq!(output["jsonPayload"]["password"], Value::from("[REDACTED]"));
+    assert_eq!(output["jsonPayload"]["span"]["foo"], Value::from("bar"));
+}
+
+#[test]
+fn replays_inner_configuration_on_clone() {
+    let configurations = Arc::new(AtomicUsize::new(0));
+    let counter = configurations.clone();
+
+    let layer = tracing_stackdriver::layer()
+        .with_structured_payload(true)
+        .map_inner(move |inner| {
+            counter.fetch_add(1, Ordering::SeqCst);
+
+            // event formatters set on the inner Layer are superseded by this Layer's own
+            inner
+                .log_internal_errors(false)
+                .map_event_format(|_| Default::default())
+        })
+        .with_redacted_fields(&["password"]);
+
+    assert_eq!(configurations.load(Ordering::SeqCst), 1);
+
+    let clone = layer.clone();
+
+    assert_eq!(configurations.load(Ordering::SeqCst), 2);
+
+    let clone_output = run(clone);
+    let output = run(layer);
+
+    assert_eq!(output, clone_output);
+    assert_eq!(output["jsonPayload"]["password"], Value::from("[REDACTED]"));
+}
@@ -1,11 +1,12 @@
 #![allow(clippy::disallowed_names)]
 use std::collections::BTreeMap;
 
-use helpers::run_with_tracing;
+use helpers::{run_with_tracing, run_with_tracing_layer};
 use mocks::{MockDefaultEvent, MockEventWithSpan};
 use serde::Deserialize;
 use time::OffsetDateTime;
-use tracing_stackdriver::LogSeverity;
+use tracing_stackdriver::{EmptyTargetMode, FieldCasing, LogSeverity};
+use tracing_subscriber::layer::SubscriberExt;
 
 mod helpers;
 mod mocks;
@@ -55,6 +56,36 @@ fn includes_custom_fields_with_dot() {
     );
 }
 
+#[test]
+fn preserves_type_fields_verbatim() {
+    let events = run_with_tracing::<BTreeMap<String, serde_json::Value>>(|| {
+        tracing::info!(
+            "@type" = "type.googleapis.com/google.cloud.audit.AuditLog",
+            "typed"
+        );
+        tracing::info!(
+            type_url = "type.googleapis.com/google.cloud.audit.AuditLog",
+            "typed"
+        );
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+
+    for event in events {
+        assert_eq!(
+            event.get("@type"),
+            Some(&serde_json::json!(
+                "type.googleapis.com/google.cloud.audit.AuditLog"
+            )),
+            "full event: {:?}",
+            event
+        );
+        assert!(!event.contains_key("type"));
+        assert!(!event.contains_key("typeUrl"));
+    }
+}
+
 #[test]
 fn handles_stringly_severity_override() {
     let events = run_with_tracing::<MockDefaultEvent>(|| {
@@ -114,3 +145,394 @@ fn includes_flattened_fields() {
     assert_eq!(event.baz, baz);
     assert_eq!(event.message, "some stackdriver message");
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MockSpanWithStartTime {
+    #[serde(deserialize_with = "time::serde::rfc3339::deserialize")]
+    started_at: OffsetDateTime,
+}
+
+#[derive(Deserialize)]
+struct MockEventWithSpanStartTime {
+    span: MockSpanWithStartTime,
+    spans: Vec<MockSpanWithStartTime>,
+}
+
+#[test]
+fn includes_span_start_times() {
+    let start = OffsetDateTime::now_utc();
+    let layer = tracing_stackdriver::layer().with_span_start_times(true);
+
+    let events = run_with_tracing_layer::<MockEventWithSpanStartTime>(layer, || {
+        let outer = tracing::info_span!("outer_span");
+        let _outer = outer.enter();
+        let inner = tracing::info_span!("inner_span");
+        let _inner = inner.enter();
+        tracing::info!("some stackdriver message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(event.span.started_at >= start);
+    assert_eq!(event.spans.len(), 2);
+    assert!(event.spans[0].started_at <= event.spans[1].started_at);
+    assert_eq!(event.spans[1].started_at, event.span.started_at);
+}
+
+#[test]
+fn renames_span_field() {
+    let layer = tracing_stackdriver::layer().with_span_field_name("currentSpan");
+
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        let span = tracing::info_span!("stackdriver_span", foo = "bar");
+        let _guard = span.enter();
+        tracing::info!("some stackdriver message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(!event.contains_key("span"));
+    assert_eq!(event["currentSpan"]["name"], "stackdriver_span");
+    assert_eq!(event["currentSpan"]["foo"], "bar");
+}
+
+#[test]
+fn includes_spans_of_explicit_parents() {
+    let events = run_with_tracing::<BTreeMap<String, serde_json::Value>>(|| {
+        let parent = tracing::info_span!("parent_span");
+        let current = tracing::info_span!(parent: None, "current_span");
+        let _current = current.enter();
+        tracing::info!(parent: &parent, "some stackdriver message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["span"]["name"], "parent_span");
+    assert_eq!(event["spans"].as_array().map(Vec::len), Some(1));
+    assert_eq!(event["spans"][0]["name"], "parent_span");
+}
+
+#[test]
+fn flattens_single_field_spans() {
+    let layer = tracing_stackdriver::layer().with_flatten_single_field_spans(true);
+
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        let single = tracing::info_span!("single_field_span", user_id = 42);
+        let _single = single.enter();
+        tracing::info!("single field");
+        let multiple = tracing::info_span!("multiple_field_span", foo = "bar", baz = "qux");
+        let _multiple = multiple.enter();
+        tracing::info!("multiple fields");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+    assert!(!events[0].contains_key("span"));
+    assert_eq!(events[0].get("userId"), Some(&serde_json::json!(42)));
+    assert_eq!(events[1]["span"]["name"], "multiple_field_span");
+    assert!(!events[1].contains_key("foo"));
+}
+
+#[test]
+fn flattens_span_fields_without_span_objects() {
+    let layer = tracing_stackdriver::layer()
+        .with_flattened_span_fields(true)
+        .with_span(false);
+
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        let span = tracing::info_span!("request", user_id = 42, trace_id = "abc123");
+        let _span = span.enter();
+        tracing::info!("flattened fields");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(!event.contains_key("span"));
+    assert_eq!(event.get("userId"), Some(&serde_json::json!(42)));
+    assert_eq!(event.get("traceId"), Some(&serde_json::json!("abc123")));
+    assert_eq!(event["spans"][0]["name"], "request");
+}
+
+#[test]
+fn prefixes_flattened_span_fields() {
+    let run = |prefix| {
+        let layer = tracing_stackdriver::layer()
+            .with_flatten_single_field_spans(true)
+            .with_span_field_prefix(prefix);
+
+        let mut events =
+            run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+                let span = tracing::info_span!("request", user = "span user");
+                let _span = span.enter();
+                tracing::info!(user = "event user", "some stackdriver message");
+            })
+            .expect("Error converting test buffer to JSON");
+
+        events.pop().expect("No event heard")
+    };
+
+    let event = run(None);
+    assert_eq!(event.get("user"), Some(&serde_json::json!("event user")));
+    assert!(!event.contains_key("spanUser"));
+
+    let event = run(Some("span"));
+    assert_eq!(event.get("user"), Some(&serde_json::json!("event user")));
+    assert_eq!(event.get("spanUser"), Some(&serde_json::json!("span user")));
+}
+
+#[test]
+fn includes_parent_span_field() {
+    let layer = tracing_stackdriver::layer().with_parent_span_field("parentSpan");
+
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        tracing::info!("outside of a span");
+        let outer = tracing::info_span!("outer_span");
+        let _outer = outer.enter();
+        let inner = tracing::info_span!("inner_span");
+        let _inner = inner.enter();
+        tracing::info!("inside of a span");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+    assert!(!events[0].contains_key("parentSpan"));
+    assert_eq!(
+        events[1].get("parentSpan"),
+        Some(&serde_json::json!("inner_span"))
+    );
+}
+
+#[test]
+fn includes_span_fields_by_name() {
+    let layer = tracing_stackdriver::layer().with_span_fields_by_name(true);
+
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        let outer = tracing::info_span!("request", user_id = 42);
+        let _outer = outer.enter();
+        let middle = tracing::info_span!("handler", route = "/users");
+        let _middle = middle.enter();
+        let inner = tracing::info_span!("handler", attempt = 2);
+        let _inner = inner.enter();
+        tracing::info!("some stackdriver message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(
+        event.get("spanFields"),
+        Some(&serde_json::json!({
+            "request": { "user_id": 42 },
+            "handler": { "route": "/users" },
+            "handler_1": { "attempt": 2 },
+        }))
+    );
+}
+
+#[test]
+fn prefers_formatted_message_over_explicit_message_field() {
+    let events = run_with_tracing::<MockEventWithFields>(|| {
+        tracing::info!(message = "explicit", baz = 1, "formatted {}", 123)
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.message, "formatted 123");
+}
+
+#[test]
+fn uses_explicit_message_field_without_formatted_message() {
+    let events =
+        run_with_tracing::<MockEventWithFields>(|| tracing::info!(message = "explicit", baz = 1))
+            .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.message, "explicit");
+}
+
+#[test]
+fn handles_empty_targets() {
+    let run = |mode| {
+        let layer = tracing_stackdriver::layer().with_empty_target(mode);
+
+        run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(
+            layer,
+            || tracing::info!(target: "", "some stackdriver message"),
+        )
+        .expect("Error converting test buffer to JSON")
+        .remove(0)
+    };
+
+    let event = run(EmptyTargetMode::Keep);
+    assert_eq!(event.get("target"), Some(&serde_json::json!("")));
+
+    let event = run(EmptyTargetMode::Omit);
+    assert!(!event.contains_key("target"));
+
+    let event = run(EmptyTargetMode::CrateName);
+    assert_eq!(event.get("target"), Some(&serde_json::json!("default")));
+}
+
+#[test]
+fn omits_targets_when_disabled() {
+    let layer = tracing_stackdriver::layer().with_include_target(false);
+
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        tracing::info!("some stackdriver message")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(event.contains_key("message"));
+    assert!(!event.contains_key("target"));
+}
+
+#[test]
+fn formats_minimal_entries() {
+    let layer = tracing_stackdriver::layer().with_minimal();
+
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        tracing::info!(
+            user_id = 123,
+            labels.is_production = true,
+            http_request.request_method = "GET",
+            "some stackdriver message"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(event.contains_key("time"));
+    assert_eq!(event.get("severity"), Some(&serde_json::json!("INFO")));
+    assert_eq!(
+        event.get("message"),
+        Some(&serde_json::json!("some stackdriver message"))
+    );
+    assert_eq!(event.get("user_id"), Some(&serde_json::json!(123)));
+    assert_eq!(
+        event.get("logging.googleapis.com/labels"),
+        Some(&serde_json::json!({ "is_production": "true" }))
+    );
+    assert_eq!(
+        event.get("httpRequest"),
+        Some(&serde_json::json!({ "requestMethod": "GET" }))
+    );
+    assert!(!event.contains_key("logging.googleapis.com/sourceLocation"));
+}
+
+#[test]
+fn reads_back_configuration() {
+    let layer = tracing_stackdriver::layer::<tracing_subscriber::Registry>();
+    assert!(layer.source_location_enabled());
+    assert!(!layer.structured_payload_enabled());
+    assert_eq!(layer.field_casing(), &FieldCasing::CamelCase);
+    assert_eq!(layer.default_severity(), LogSeverity::Default);
+
+    let layer = layer
+        .with_minimal()
+        .with_structured_payload(true)
+        .with_default_severity(LogSeverity::Info);
+
+    assert!(!layer.source_location_enabled());
+    assert!(layer.structured_payload_enabled());
+    assert_eq!(layer.field_casing(), &FieldCasing::Preserve);
+    assert_eq!(layer.default_severity(), LogSeverity::Info);
+}
+
+#[test]
+fn applies_configured_span_field_casing() {
+    let emit = || {
+        tracing::info_span!("stackdriver_span", user_id = 42)
+            .in_scope(|| tracing::info!("some stackdriver message"));
+    };
+
+    let preserved = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(
+        tracing_stackdriver::layer(),
+        emit,
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let layer = tracing_stackdriver::layer().with_span_field_casing(FieldCasing::CamelCase);
+    let camel_cased = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, emit)
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(preserved[0]["span"]["user_id"], 42);
+    assert_eq!(camel_cased[0]["span"]["userId"], 42);
+    assert_eq!(camel_cased[0]["spans"][0]["userId"], 42);
+    assert!(camel_cased[0]["span"].get("user_id").is_none());
+}
+
+/// Writer that fails every write, which the inner fmt::Layer reports as an internal error
+struct FailingWriter;
+
+impl std::io::Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("write failed"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Environment variable that runs `maps_inner_layers` as a child process, with the value of
+/// `log_internal_errors` to configure
+const LOG_INTERNAL_ERRORS: &str = "TRACING_STACKDRIVER_TEST_LOG_INTERNAL_ERRORS";
+
+#[test]
+fn maps_inner_layers() {
+    // internal errors are written straight to the process' stderr, so they're captured by
+    // re-running this test in a child process
+    if let Some(log_internal_errors) = std::env::var_os(LOG_INTERNAL_ERRORS) {
+        let log_internal_errors = log_internal_errors == "true";
+
+        // the inner configuration is replayed when the writer is replaced and the Layer is cloned
+        let layer = tracing_stackdriver::layer::<tracing_subscriber::Registry>()
+            .map_inner(move |inner| inner.log_internal_errors(log_internal_errors))
+            .with_writer(|| FailingWriter)
+            .clone();
+
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("some stackdriver message")
+        });
+
+        return;
+    }
+
+    let run = |log_internal_errors: bool| {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["maps_inner_layers", "--exact", "--nocapture"])
+            .env(LOG_INTERNAL_ERRORS, log_internal_errors.to_string())
+            .output()
+            .expect("Error running child test process");
+
+        assert!(output.status.success(), "Child test process failed");
+        String::from_utf8(output.stderr).expect("Invalid UTF-8 output")
+    };
+
+    assert!(run(true).contains("Unable to write an event"));
+    assert!(!run(false).contains("Unable to write an event"));
+}
+
+#[test]
+fn omits_span_start_times_by_default() {
+    let events = run_with_tracing::<serde_json::Map<String, serde_json::Value>>(|| {
+        let span = tracing::info_span!("stackdriver_span");
+        let _span = span.enter();
+        tracing::info!("some stackdriver message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event["span"],
+        serde_json::json!({ "name": "stackdriver_span" })
+    );
+    assert_eq!(
+        event["spans"],
+        serde_json::json!([{ "name": "stackdriver_span" }])
+    );
+}
@@ -0,0 +1,54 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+struct FailingWriter;
+
+impl io::Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("write failed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn counts_failed_entries() {
+    let counter = Arc::new(AtomicU64::new(0));
+
+    let layer = tracing_stackdriver::layer()
+        .with_writer(|| FailingWriter)
+        .with_error_counter(counter.clone());
+
+    let subscriber = Registry::default().with(layer);
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!("first");
+        tracing::warn!("second");
+        tracing::error!("third");
+    });
+
+    assert_eq!(counter.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn does_not_count_successful_entries() {
+    let counter = Arc::new(AtomicU64::new(0));
+
+    let layer = tracing_stackdriver::layer()
+        .with_writer(io::sink)
+        .with_error_counter(counter.clone());
+
+    let subscriber = Registry::default().with(layer);
+
+    tracing::subscriber::with_default(subscriber, || tracing::info!("written"));
+
+    assert_eq!(counter.load(Ordering::Relaxed), 0);
+}
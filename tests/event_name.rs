@@ -0,0 +1,29 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use serde_json::{json, Map, Value};
+
+mod helpers;
+
+#[test]
+fn includes_event_name_field() {
+    let layer = tracing_stackdriver::layer().with_event_name_field(Some("eventName"));
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(
+        layer,
+        || tracing::event!(name: "my_event", tracing::Level::INFO, "some stackdriver message"),
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.get("eventName"), Some(&json!("my_event")));
+}
+
+#[test]
+fn omits_event_name_field_by_default() {
+    let events = run_with_tracing::<Map<String, Value>>(
+        || tracing::event!(name: "my_event", tracing::Level::INFO, "some stackdriver message"),
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(!event.values().any(|value| value == "my_event"));
+}
@@ -0,0 +1,77 @@
+use helpers::MockWriter;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    Layer, Registry,
+};
+
+mod helpers;
+
+#[derive(Serialize)]
+struct RequestContext {
+    tenant: &'static str,
+    user_id: u64,
+}
+
+/// Stores a `RequestContext` in the extensions of every `request` span
+struct RequestContextLayer;
+
+impl<S> Layer<S> for RequestContextLayer
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::span::Id,
+        context: Context<'_, S>,
+    ) {
+        if attrs.metadata().name() == "request" {
+            if let Some(span) = context.span(id) {
+                span.extensions_mut().insert(RequestContext {
+                    tenant: "acme",
+                    user_id: 42,
+                });
+            }
+        }
+    }
+}
+
+#[test]
+fn promotes_span_extensions() {
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let layer = tracing_stackdriver::layer()
+        .with_extension_promoter::<RequestContext>("context")
+        .with_writer(move || MockWriter(shared.clone()));
+
+    let subscriber = Registry::default().with(RequestContextLayer).with(layer);
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("request").in_scope(|| {
+            tracing::info_span!("inner").in_scope(|| tracing::info!("handling request"));
+        });
+
+        tracing::info!("outside of a request");
+    });
+
+    let buffer = buffer
+        .lock()
+        .expect("Couldn't get lock on test write target");
+    let events: Vec<serde_json::Value> = serde_json::Deserializer::from_slice(&buffer)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("Error converting test buffer to JSON");
+    let [inside, outside] = events.as_slice() else {
+        panic!("Expected two events, found {}", events.len());
+    };
+
+    assert_eq!(
+        inside["context"],
+        json!({ "tenant": "acme", "user_id": 42 })
+    );
+    assert_eq!(outside.get("context"), None);
+}
@@ -0,0 +1,30 @@
+use helpers::run_with_tracing_layer;
+use serde_json::{json, Map, Value};
+
+mod helpers;
+
+#[test]
+fn routes_fields_through_custom_handlers() {
+    let layer =
+        tracing_stackdriver::layer().with_field_handler("metric", "metrics", |key, value| {
+            (key.to_uppercase(), value)
+        });
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(
+            metric.requests = 5,
+            metric.errors = 1,
+            metrics_enabled = true,
+            "some stackdriver message"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.get("metrics"),
+        Some(&json!({ "REQUESTS": 5, "ERRORS": 1 }))
+    );
+    assert_eq!(event.get("metricsEnabled"), Some(&json!(true)));
+    assert!(!event.contains_key("metricRequests"));
+}
@@ -0,0 +1,28 @@
+use helpers::run_with_tracing_layer_raw;
+use tracing_stackdriver::FieldOrder;
+
+mod helpers;
+
+#[test]
+fn orders_fields_by_field_order() {
+    let run = |field_order| {
+        let layer = tracing_stackdriver::layer().with_field_order(field_order);
+        let output = run_with_tracing_layer_raw(layer, || {
+            tracing::info!(zebra = 1, apple = 2, mango = 3, "some stackdriver message")
+        });
+        let output = String::from_utf8(output).expect("Invalid UTF-8");
+
+        let mut keys = ["\"message\"", "\"zebra\"", "\"apple\"", "\"mango\""];
+        keys.sort_by_key(|key| output.find(key).expect("Missing field"));
+        keys
+    };
+
+    assert_eq!(
+        run(FieldOrder::Alphabetical),
+        ["\"apple\"", "\"mango\"", "\"message\"", "\"zebra\""]
+    );
+    assert_eq!(
+        run(FieldOrder::InsertionOrder),
+        ["\"message\"", "\"zebra\"", "\"apple\"", "\"mango\""]
+    );
+}
@@ -0,0 +1,114 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+use tracing_stackdriver::{Layer, LogSeverity};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+/// Operations received by the writers of a Layer, recorded with the index of the writer that
+/// received them
+type Operations = Arc<Mutex<Vec<(usize, &'static str)>>>;
+
+/// MakeWriter creating a new `RecordingWriter` for every entry
+type MakeRecordingWriter = Box<dyn Fn() -> RecordingWriter + Send + Sync>;
+
+/// Writer that records each write and flush it receives
+struct RecordingWriter(usize, Operations);
+
+impl io::Write for RecordingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.1.lock().unwrap().push((self.0, "write"));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.1.lock().unwrap().push((self.0, "flush"));
+        Ok(())
+    }
+}
+
+fn run<W>(
+    configure: impl FnOnce(Layer<Registry, MakeRecordingWriter>) -> Layer<Registry, W>,
+    callback: impl FnOnce(),
+) -> Vec<(usize, &'static str)>
+where
+    W: for<'writer> tracing_subscriber::fmt::MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let operations = Operations::default();
+    let shared = operations.clone();
+    let writers = AtomicUsize::new(0);
+
+    // every entry is written through a new writer, like a per-call buffered writer
+    let make_writer: MakeRecordingWriter =
+        Box::new(move || RecordingWriter(writers.fetch_add(1, Ordering::SeqCst), shared.clone()));
+
+    let layer = configure(tracing_stackdriver::layer().with_writer(make_writer));
+    let subscriber = Registry::default().with(layer);
+
+    tracing::subscriber::with_default(subscriber, callback);
+
+    let operations = operations.lock().unwrap();
+    operations.clone()
+}
+
+#[test]
+fn flushes_entries_at_or_above_severity() {
+    let configure = |layer: Layer<_, _>| layer.with_flush_on_severity(LogSeverity::Warning);
+
+    assert_eq!(run(configure, || tracing::info!("info")), [(0, "write")]);
+    assert_eq!(
+        run(configure, || tracing::error!("error")),
+        [(0, "write"), (0, "flush")]
+    );
+}
+
+#[test]
+fn flushes_the_writer_of_each_entry() {
+    let operations = run(
+        |layer| layer.with_flush_on_severity(LogSeverity::Warning),
+        || {
+            tracing::error!("first");
+            tracing::error!("second");
+        },
+    );
+
+    assert_eq!(
+        operations,
+        [(0, "write"), (0, "flush"), (1, "write"), (1, "flush")]
+    );
+}
+
+#[test]
+fn skips_flushing_dropped_entries() {
+    let muted = run(
+        |layer| {
+            layer
+                .with_muted_severities(&[LogSeverity::Error])
+                .with_flush_on_severity(LogSeverity::Warning)
+        },
+        || tracing::error!("error"),
+    );
+
+    assert!(muted.is_empty());
+
+    let sampled_out = run(
+        |layer| {
+            layer
+                .with_debug_sampling(0.0)
+                .with_flush_on_severity(LogSeverity::Debug)
+        },
+        || tracing::debug!("debug"),
+    );
+
+    assert!(sampled_out.is_empty());
+}
+
+#[test]
+fn never_flushes_by_default() {
+    let operations = run(|layer| layer, || tracing::error!("error"));
+
+    assert_eq!(operations, [(0, "write")]);
+}
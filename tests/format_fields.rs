@@ -0,0 +1,105 @@
+use serde_json::json;
+use tracing_stackdriver::LogSeverity;
+use tracing_subscriber::Registry;
+
+fn fields(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    match value {
+        serde_json::Value::Object(fields) => fields,
+        value => panic!("Expected an object, found {value}"),
+    }
+}
+
+#[test]
+fn formats_labels_without_a_subscriber() {
+    let layer = tracing_stackdriver::layer::<Registry>();
+
+    let entry = layer
+        .format_fields(
+            LogSeverity::Info,
+            "labelled message",
+            fields(json!({ "labels.request_id": "abc", "labels.retries": 2 })),
+            Some((
+                "request",
+                fields(json!({ "labels.region": "us-east1", "id": 1 })),
+            )),
+        )
+        .expect("Error formatting fields");
+
+    let entry: serde_json::Value = serde_json::from_str(&entry).expect("Invalid JSON entry");
+
+    assert_eq!(entry["severity"], json!("INFO"));
+    assert_eq!(entry["message"], json!("labelled message"));
+    assert_eq!(entry["span"], json!({ "id": 1, "name": "request" }));
+    assert_eq!(
+        entry["logging.googleapis.com/labels"],
+        json!({ "region": "us-east1", "requestId": "abc", "retries": "2" })
+    );
+}
+
+#[test]
+fn formats_http_requests_without_a_subscriber() {
+    let layer = tracing_stackdriver::layer::<Registry>();
+
+    let entry = layer
+        .format_fields(
+            LogSeverity::Warning,
+            "http message",
+            fields(json!({
+                "http_request.request_method": "GET",
+                "http_request.status": 404,
+                "other": true,
+            })),
+            None,
+        )
+        .expect("Error formatting fields");
+
+    let entry: serde_json::Value = serde_json::from_str(&entry).expect("Invalid JSON entry");
+
+    assert_eq!(entry["severity"], json!("WARNING"));
+    assert_eq!(entry["other"], json!(true));
+    assert_eq!(
+        entry["httpRequest"],
+        json!({ "requestMethod": "GET", "status": 404 })
+    );
+    assert!(entry.get("span").is_none());
+}
+
+#[test]
+fn formats_fields_like_events() {
+    let layer = tracing_stackdriver::layer::<Registry>()
+        .with_project_id("my-project-id")
+        .with_platform(tracing_stackdriver::Platform::Gke)
+        .with_muted_severities(&[LogSeverity::Notice]);
+
+    let entry = layer
+        .format_fields(
+            LogSeverity::Info,
+            "inherited message",
+            serde_json::Map::new(),
+            Some((
+                "request",
+                fields(json!({ "insert_id": "abc", "trace_id": "def" })),
+            )),
+        )
+        .expect("Error formatting fields");
+
+    let entry: serde_json::Value = serde_json::from_str(&entry).expect("Invalid JSON entry");
+
+    assert_eq!(entry["resource"]["type"], json!("k8s_container"));
+    assert_eq!(entry["logging.googleapis.com/insertId"], json!("abc"));
+    assert_eq!(
+        entry["logging.googleapis.com/trace"],
+        json!("projects/my-project-id/traces/def")
+    );
+
+    let entry = layer
+        .format_fields(
+            LogSeverity::Info,
+            "muted message",
+            serde_json::Map::new(),
+            Some(("request", fields(json!({ "min_severity": "notice" })))),
+        )
+        .expect("Error formatting fields");
+
+    assert_eq!(entry, "");
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "http")]
+use helpers::run_with_tracing;
+use mocks::MockDefaultEvent;
+use std::collections::BTreeMap;
+
+mod helpers;
+mod mocks;
+
+fn headers() -> http::HeaderMap {
+    let mut headers = http::HeaderMap::new();
+    headers.insert("user-agent", "curl/8.0".parse().unwrap());
+    headers.insert("x-request-id", "abc123".parse().unwrap());
+    headers.insert("authorization", "Bearer secret".parse().unwrap());
+    headers.insert("accept", "*/*".parse().unwrap());
+    headers
+}
+
+#[test]
+fn collects_allowlisted_headers() {
+    let labels = tracing_stackdriver::header_labels(&headers(), &["User-Agent", "x-request-id"]);
+
+    let expected = BTreeMap::from([
+        ("user-agent".to_owned(), "curl/8.0".to_owned()),
+        ("x-request-id".to_owned(), "abc123".to_owned()),
+    ]);
+
+    assert_eq!(labels, expected);
+    assert!(!labels.contains_key("authorization"));
+}
+
+#[test]
+fn redacts_sensitive_headers() {
+    let labels = tracing_stackdriver::header_labels(&headers(), &["authorization", "cookie"]);
+
+    assert_eq!(
+        labels,
+        BTreeMap::from([("authorization".to_owned(), "[REDACTED]".to_owned())])
+    );
+}
+
+#[test]
+fn records_header_labels() {
+    let labels = tracing_stackdriver::header_labels(&headers(), &["x-request-id"]);
+
+    let events = run_with_tracing::<MockDefaultEvent>(
+        || tracing::info!(labels = ?labels, labels.env = "prod", "request received"),
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.labels["xRequestId"], "abc123");
+    assert_eq!(event.labels["env"], "prod");
+}
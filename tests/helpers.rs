@@ -26,6 +26,15 @@ pub fn run_with_tracing_layer<E>(
 where
     E: for<'a> Deserialize<'a>,
 {
+    let buffer = run_with_tracing_layer_raw(layer, callback);
+
+    serde_json::Deserializer::from_slice(&buffer)
+        .into_iter()
+        .collect()
+}
+
+/// Run a traced callback against a Layer configuration, returning the raw output
+pub fn run_with_tracing_layer_raw(layer: Layer<Registry>, callback: impl FnOnce()) -> Vec<u8> {
     let buffer = Arc::new(Mutex::new(vec![]));
     let shared = buffer.clone();
     let make_writer = move || MockWriter(shared.clone());
@@ -38,9 +47,7 @@ where
         .lock()
         .expect("Couldn't get lock on test write target");
 
-    serde_json::Deserializer::from_slice(&buffer)
-        .into_iter()
-        .collect()
+    buffer.clone()
 }
 
 // FIXME: make this entirely internal
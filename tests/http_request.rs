@@ -1,5 +1,6 @@
-use helpers::run_with_tracing;
+use helpers::{run_with_tracing, run_with_tracing_layer};
 use mocks::{MockHttpEvent, MockHttpRequest};
+use tracing_stackdriver::IpFormat;
 
 mod helpers;
 mod mocks;
@@ -32,3 +33,57 @@ fn nests_http_request() {
     let event = events.first().expect("No event heard");
     assert_eq!(event.http_request, mock_http_request);
 }
+
+#[test]
+fn inherits_http_request_fields_from_spans() {
+    let events = run_with_tracing::<serde_json::Map<String, serde_json::Value>>(|| {
+        let span = tracing::info_span!(
+            "request",
+            http_request.request_method = "GET",
+            http_request.status = tracing::field::Empty,
+            route = "/users"
+        );
+
+        let _span = span.enter();
+        span.record("http_request.status", 404);
+        tracing::info!(http_request.latency = "0.1s", "some stackdriver message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event["httpRequest"],
+        serde_json::json!({ "requestMethod": "GET", "status": 404, "latency": "0.1s" })
+    );
+    assert_eq!(
+        event["span"],
+        serde_json::json!({ "name": "request", "route": "/users" })
+    );
+}
+
+#[test]
+fn formats_http_request_ips() {
+    let run = |ip_format| {
+        let layer = tracing_stackdriver::layer().with_ip_format(ip_format);
+
+        run_with_tracing_layer::<serde_json::Map<String, serde_json::Value>>(layer, || {
+            tracing::info!(
+                http_request.remote_ip = "2001:db8::1",
+                http_request.server_ip = "10.0.0.1",
+                "some stackdriver message"
+            )
+        })
+        .expect("Error converting test buffer to JSON")
+        .remove(0)
+    };
+
+    let event = run(IpFormat::Default);
+    assert_eq!(event["httpRequest"]["remoteIp"], "2001:db8::1");
+
+    let event = run(IpFormat::ExpandedV6);
+    assert_eq!(
+        event["httpRequest"]["remoteIp"],
+        "2001:0db8:0000:0000:0000:0000:0000:0001"
+    );
+    assert_eq!(event["httpRequest"]["serverIp"], "10.0.0.1");
+}
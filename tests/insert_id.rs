@@ -1,5 +1,6 @@
-use helpers::run_with_tracing;
+use helpers::{run_with_tracing, run_with_tracing_layer};
 use mocks::MockDefaultEvent;
+use std::collections::BTreeMap;
 
 mod helpers;
 mod mocks;
@@ -36,3 +37,64 @@ fn omits_insert_id_by_default() {
     let event = events.first().expect("No event heard");
     assert!(event.insert_id.is_none());
 }
+
+#[test]
+fn inherits_insert_ids_from_spans() {
+    let events = run_with_tracing::<MockDefaultEvent>(|| {
+        let span = tracing::info_span!("request", insert_id = "request-123");
+        let _span = span.enter();
+        tracing::info!("first");
+        tracing::info!("second");
+        tracing::info!(insert_id = "event-456", "overridden");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let insert_ids: Vec<_> = events
+        .iter()
+        .map(|event| event.insert_id.as_deref())
+        .collect();
+
+    assert_eq!(
+        insert_ids,
+        [Some("request-123"), Some("request-123"), Some("event-456")]
+    );
+}
+
+#[test]
+fn copies_request_ids_into_labels_and_insert_ids() {
+    let layer = tracing_stackdriver::layer().with_request_id_field("request_id");
+
+    let events = run_with_tracing_layer::<MockDefaultEvent>(layer, || {
+        tracing::info!(request_id = "request-123", "hello!");
+        tracing::info!(
+            request_id = "request-456",
+            insert_id = "event-789",
+            "hello!"
+        );
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].insert_id.as_deref(), Some("request-123"));
+    assert_eq!(
+        events[0].labels.get("request_id").map(String::as_str),
+        Some("request-123")
+    );
+
+    assert_eq!(events[1].insert_id.as_deref(), Some("event-789"));
+    assert_eq!(
+        events[1].labels.get("request_id").map(String::as_str),
+        Some("request-456")
+    );
+}
+
+#[test]
+fn recognizes_camel_cased_insert_ids() {
+    let events = run_with_tracing::<BTreeMap<String, serde_json::Value>>(|| {
+        tracing::info!(insertId = "abc", "hello!")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["logging.googleapis.com/insertId"], "abc");
+    assert!(!event.contains_key("insertId"));
+}
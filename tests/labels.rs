@@ -1,6 +1,13 @@
-use helpers::run_with_tracing;
+use helpers::{run_with_tracing, run_with_tracing_layer, MockWriter};
 use mocks::MockDefaultEvent;
-use std::collections::BTreeMap;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+use tracing::instrument::WithSubscriber;
+use tracing_stackdriver::{BoolLabelFormat, LabelsMode, LogSeverity};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
 
 mod helpers;
 mod mocks;
@@ -56,3 +63,301 @@ fn omits_labels_by_default() {
     let event = events.first().expect("No event heard");
     assert!(event.labels.is_empty());
 }
+
+#[tracing::instrument(fields(labels.env = "prod", labels.region = "us-east1"))]
+fn instrumented_with_labels() {
+    tracing::info!(labels.region = "europe-west1", "hello!");
+}
+
+#[derive(Debug, Deserialize)]
+struct MockEventWithLabeledSpan {
+    #[serde(rename = "logging.googleapis.com/labels", default)]
+    labels: BTreeMap<String, String>,
+    span: BTreeMap<String, serde_json::Value>,
+}
+
+#[test]
+fn inherits_span_labels() {
+    let events = run_with_tracing::<MockEventWithLabeledSpan>(instrumented_with_labels)
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.labels.get("env").map(String::as_str), Some("prod"));
+    assert_eq!(
+        event.labels.get("region").map(String::as_str),
+        Some("europe-west1")
+    );
+    assert!(!event.span.contains_key("labels.env"));
+    assert_eq!(
+        event.span.get("name"),
+        Some(&serde_json::json!("instrumented_with_labels"))
+    );
+}
+
+#[test]
+fn limits_label_count_and_length() {
+    let mut fields = serde_json::Map::new();
+
+    for index in 0..100 {
+        fields.insert(
+            format!("labels.label_{index:03}"),
+            serde_json::Value::from(index),
+        );
+    }
+
+    fields.insert(
+        "labels.long".to_owned(),
+        serde_json::Value::from("x".repeat(100_000)),
+    );
+
+    let entry = tracing_stackdriver::layer::<tracing_subscriber::Registry>()
+        .format_fields(LogSeverity::Info, "hello!", fields, None)
+        .expect("Error formatting fields");
+
+    let event: serde_json::Value = serde_json::from_str(&entry).expect("Invalid JSON entry");
+    let labels = event["logging.googleapis.com/labels"]
+        .as_object()
+        .expect("No labels");
+
+    assert_eq!(labels.len(), 64);
+    assert!(labels.contains_key("label000"));
+    assert!(!labels.contains_key("label099"));
+    assert!(!labels.contains_key("long"));
+    assert_eq!(event["labelsTruncated"], serde_json::json!(37));
+}
+
+#[test]
+fn stringifies_numeric_label_values_canonically() {
+    let events = run_with_tracing::<MockDefaultEvent>(|| {
+        tracing::info!(
+            labels.integer = 42,
+            labels.whole_float = 1.0,
+            labels.float = 0.25,
+            labels.negative_float = -1.5,
+            labels.large = u64::MAX,
+            "hello!"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.labels["integer"], "42");
+    assert_eq!(event.labels["wholeFloat"], "1");
+    assert_eq!(event.labels["float"], "0.25");
+    assert_eq!(event.labels["negativeFloat"], "-1.5");
+    assert_eq!(event.labels["large"], "18446744073709551615");
+}
+
+#[test]
+fn includes_version_label() {
+    let layer = tracing_stackdriver::layer().with_version_label("1.2.3");
+
+    let events = run_with_tracing_layer::<MockDefaultEvent>(layer, || {
+        tracing::info!("versioned");
+        tracing::info!(labels.version = "override", "overridden");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].labels["version"], "1.2.3");
+    assert_eq!(events[1].labels["version"], "override");
+}
+
+#[test]
+fn places_labels_by_labels_mode() {
+    let emit = || tracing::info!(labels.deploy_env = "prod", user = "someone", "hello!");
+
+    let google = run_with_tracing_layer::<serde_json::Value>(
+        tracing_stackdriver::layer().with_labels_mode(LabelsMode::Google),
+        emit,
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let root_fields = run_with_tracing_layer::<serde_json::Value>(
+        tracing_stackdriver::layer().with_labels_mode(LabelsMode::RootFields),
+        emit,
+    )
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        google[0]["logging.googleapis.com/labels"]["deployEnv"],
+        "prod"
+    );
+    assert!(google[0].get("deployEnv").is_none());
+
+    assert_eq!(root_fields[0]["deployEnv"], "prod");
+    assert_eq!(root_fields[0]["user"], "someone");
+    assert!(root_fields[0]
+        .get("logging.googleapis.com/labels")
+        .is_none());
+}
+
+#[test]
+fn merges_raw_json_labels_objects_into_labels() {
+    let events = run_with_tracing::<BTreeMap<String, serde_json::Value>>(|| {
+        tracing::info!(
+            __raw_json = r#"{"labels": {"team": "core", "region": "us"}, "other": 1}"#,
+            labels.region = "eu",
+            "hello!"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(
+        event["logging.googleapis.com/labels"],
+        serde_json::json!({ "team": "core", "region": "eu" })
+    );
+    assert_eq!(event["other"], 1);
+    assert!(!event.contains_key("labels"));
+}
+
+/// Yields to the executor once, suspending the task across an await point
+async fn yield_now() {
+    let mut yielded = false;
+
+    std::future::poll_fn(|context| {
+        if yielded {
+            std::task::Poll::Ready(())
+        } else {
+            yielded = true;
+            context.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    })
+    .await
+}
+
+#[test]
+fn inherits_span_labels_across_await_points() {
+    use tracing::Instrument;
+
+    let events = run_with_tracing::<MockDefaultEvent>(|| {
+        let task = async {
+            yield_now().await;
+            tracing::info!("resumed");
+        };
+
+        futures_executor::block_on(task.instrument(tracing::info_span!("task", labels.task = "x")));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.labels["task"], "x");
+}
+
+#[test]
+fn promotes_grpc_fields_to_labels() {
+    let emit = || {
+        tracing::info!(
+            grpc.method = "/helloworld.Greeter/SayHello",
+            grpc.status = 5,
+            "hello!"
+        )
+    };
+
+    let default = run_with_tracing::<BTreeMap<String, serde_json::Value>>(emit)
+        .expect("Error converting test buffer to JSON");
+
+    assert!(!default[0].contains_key("logging.googleapis.com/labels"));
+
+    let layer = tracing_stackdriver::layer().with_grpc_labels(true);
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, emit)
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event["logging.googleapis.com/labels"],
+        serde_json::json!({
+            "grpcMethod": "/helloworld.Greeter/SayHello",
+            "grpcStatus": "NOT_FOUND",
+        })
+    );
+    assert!(!event.contains_key("grpc.method"));
+}
+
+#[test]
+fn formats_bool_labels() {
+    let emit = || tracing::info!(labels.enabled = false, labels.visible = true, "hello!");
+
+    let events =
+        run_with_tracing::<MockDefaultEvent>(emit).expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].labels["enabled"], "false");
+    assert_eq!(events[0].labels["visible"], "true");
+
+    let layer = tracing_stackdriver::layer().with_bool_label_format(BoolLabelFormat::ZeroOne);
+    let events = run_with_tracing_layer::<MockDefaultEvent>(layer, emit)
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].labels["enabled"], "0");
+    assert_eq!(events[0].labels["visible"], "1");
+}
+
+thread_local! {
+    static SESSION_ID: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+#[test]
+fn includes_dynamic_labels() {
+    let layer = tracing_stackdriver::layer()
+        .with_dynamic_label("session_id", || SESSION_ID.with(|id| id.borrow().clone()));
+
+    let events = run_with_tracing_layer::<MockDefaultEvent>(layer, || {
+        tracing::info!("before session");
+        SESSION_ID.with(|id| *id.borrow_mut() = Some("abc".to_owned()));
+        tracing::info!("within session");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert!(!events[0].labels.contains_key("sessionId"));
+    assert_eq!(events[1].labels["sessionId"], "abc");
+}
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn includes_task_local_dynamic_labels() {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let shared = buffer.clone();
+
+    let layer = tracing_stackdriver::layer()
+        .with_dynamic_label("request_id", || REQUEST_ID.try_with(Clone::clone).ok())
+        .with_writer(move || MockWriter(shared.clone()));
+
+    let subscriber = Registry::default().with(layer);
+
+    let request = async {
+        tracing::info!("before request");
+
+        REQUEST_ID
+            .scope("abc".to_owned(), async {
+                tracing::info!("before await");
+                // yielding lets the task resume on either worker thread
+                for _ in 0..10 {
+                    tokio::task::yield_now().await;
+                }
+                tracing::info!("after await");
+            })
+            .await;
+    };
+
+    tokio::spawn(request.with_subscriber(subscriber))
+        .await
+        .expect("Error running request task");
+
+    let buffer = buffer
+        .lock()
+        .expect("Couldn't get lock on test write target");
+    let events: Vec<MockDefaultEvent> = serde_json::Deserializer::from_slice(&buffer)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 3);
+    assert!(!events[0].labels.contains_key("requestId"));
+    assert_eq!(events[1].labels["requestId"], "abc");
+    assert_eq!(events[2].labels["requestId"], "abc");
+}
@@ -0,0 +1,31 @@
+use helpers::run_with_tracing_layer_raw;
+use tracing_stackdriver::LineEnding;
+
+mod helpers;
+
+fn run(layer: tracing_stackdriver::Layer<tracing_subscriber::Registry>) -> String {
+    let output = run_with_tracing_layer_raw(layer, || {
+        tracing::info!("first");
+        tracing::info!("second");
+    });
+
+    String::from_utf8(output).expect("Invalid UTF-8 output")
+}
+
+#[test]
+fn terminates_entries_with_line_feeds_by_default() {
+    let output = run(tracing_stackdriver::layer());
+
+    assert!(output.ends_with("}\n"));
+    assert!(!output.contains('\r'));
+    assert_eq!(output.matches('\n').count(), 2);
+}
+
+#[test]
+fn terminates_entries_with_configured_line_ending() {
+    let output = run(tracing_stackdriver::layer().with_line_ending(LineEnding::CrLf));
+
+    assert!(output.ends_with("}\r\n"));
+    assert_eq!(output.matches("\r\n").count(), 2);
+    assert_eq!(output.matches('\n').count(), 2);
+}
@@ -0,0 +1,262 @@
+use helpers::{run_with_tracing, run_with_tracing_layer_raw};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, time::SystemTime};
+use tracing_stackdriver::{LogEntry, LogEntryResource, LogEntrySourceLocation, LogSeverity};
+use tracing_subscriber::Registry;
+
+mod helpers;
+
+#[test]
+fn serializes_log_entries() {
+    let fields = vec![("userId".into(), Value::from(42))];
+
+    let mut http_request = Map::new();
+    http_request.insert("requestMethod".into(), Value::from("GET"));
+
+    let mut labels = BTreeMap::new();
+    labels.insert("env".into(), "production".into());
+
+    let entry = LogEntry {
+        time: Some(SystemTime::UNIX_EPOCH),
+        receive_timestamp: Some(SystemTime::UNIX_EPOCH),
+        resource: Some(LogEntryResource {
+            resource_type: "global".into(),
+            labels: BTreeMap::new(),
+        }),
+        log_name: Some("projects/my-project/logs/replay".into()),
+        severity: LogSeverity::Notice,
+        numeric_severity: true,
+        message: Some("replayed".into()),
+        source_location: Some(LogEntrySourceLocation {
+            file: "src/main.rs".into(),
+            line: Some(12),
+            function: None,
+        }),
+        insert_id: Some("insert-123".into()),
+        trace: Some("projects/my-project/traces/abc".into()),
+        span_id: Some("def".into()),
+        trace_sampled: true,
+        http_request,
+        labels,
+        fields,
+        json_payload: false,
+    };
+
+    let output = serde_json::to_string(&entry).expect("Error serializing LogEntry");
+    let output: Value = serde_json::from_str(&output).expect("Error parsing LogEntry JSON");
+
+    assert_eq!(
+        output,
+        serde_json::json!({
+            "time": "1970-01-01T00:00:00Z",
+            "receiveTimestamp": "1970-01-01T00:00:00Z",
+            "resource": { "type": "global" },
+            "logName": "projects/my-project/logs/replay",
+            "severity": "NOTICE",
+            "severityNumber": 300,
+            "message": "replayed",
+            "logging.googleapis.com/sourceLocation": { "file": "src/main.rs", "line": "12" },
+            "logging.googleapis.com/insertId": "insert-123",
+            "logging.googleapis.com/trace": "projects/my-project/traces/abc",
+            "logging.googleapis.com/spanId": "def",
+            "logging.googleapis.com/trace_sampled": true,
+            "userId": 42,
+            "httpRequest": { "requestMethod": "GET" },
+            "logging.googleapis.com/labels": { "env": "production" },
+        })
+    );
+}
+
+#[test]
+fn matches_the_shape_of_emitted_entries() {
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing::info!(
+            insert_id = "insert-123",
+            labels.env = "production",
+            http_request.request_method = "GET",
+            user_id = 42,
+            "hello!"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let mut event = events.into_iter().next().expect("No event heard");
+    event.remove("time");
+    event.remove("logging.googleapis.com/sourceLocation");
+
+    let fields = vec![
+        ("target".into(), Value::from("log_entry")),
+        ("userId".into(), Value::from(42)),
+    ];
+
+    let mut http_request = Map::new();
+    http_request.insert("requestMethod".into(), Value::from("GET"));
+
+    let mut labels = BTreeMap::new();
+    labels.insert("env".into(), "production".into());
+
+    let entry = LogEntry {
+        severity: LogSeverity::Info,
+        message: Some("hello!".into()),
+        insert_id: Some("insert-123".into()),
+        http_request,
+        labels,
+        fields,
+        ..Default::default()
+    };
+
+    assert_eq!(Value::Object(event), serde_json::to_value(&entry).unwrap());
+}
+
+/// Entry expected for the Event emitted by `emit_event` and the fields of `recorded_fields`,
+/// covering every LogEntry field that doesn't require OpenTelemetry
+fn expected_entry() -> LogEntry {
+    // formatted entries keep their message among their other fields, ordered by key
+    let fields = vec![
+        ("isAdmin".into(), Value::from(false)),
+        ("message".into(), Value::from("hello!")),
+        ("userId".into(), Value::from(42)),
+    ];
+
+    let mut http_request = Map::new();
+    http_request.insert("requestMethod".into(), Value::from("GET"));
+    http_request.insert("status".into(), Value::from(200));
+
+    let mut labels = BTreeMap::new();
+    labels.insert("env".into(), "production".into());
+
+    LogEntry {
+        severity: LogSeverity::Notice,
+        insert_id: Some("insert-123".into()),
+        trace: Some("projects/my-project/traces/abc".into()),
+        trace_sampled: true,
+        http_request,
+        labels,
+        fields,
+        ..Default::default()
+    }
+}
+
+/// Fields of the expected entry, keyed by the names they're recorded with
+fn recorded_fields() -> Map<String, Value> {
+    json!({
+        "severity": "notice",
+        "insert_id": "insert-123",
+        "trace_id": "abc",
+        "trace_sampled": true,
+        "http_request.request_method": "GET",
+        "http_request.status": 200,
+        "labels.env": "production",
+        "user_id": 42,
+        "is_admin": false,
+    })
+    .as_object()
+    .cloned()
+    .unwrap()
+}
+
+/// Removes the leading timestamp of a serialized entry, which differs between entries formatted
+/// at different times
+fn without_time(entry: &str) -> String {
+    let parsed: Map<String, Value> = serde_json::from_str(entry).expect("Error parsing entry");
+    let time = parsed["time"].as_str().expect("Entry has no timestamp");
+
+    entry.replacen(&format!("\"time\":\"{time}\","), "", 1)
+}
+
+#[test]
+fn matches_entries_formatted_from_events() {
+    let mut line = 0;
+
+    let layer = tracing_stackdriver::layer()
+        .with_project_id("my-project")
+        .with_include_target(false);
+
+    let output = run_with_tracing_layer_raw(layer, || {
+        line = line!() + 1;
+        tracing::info!(
+            severity = "notice",
+            insert_id = "insert-123",
+            trace_id = "abc",
+            trace_sampled = true,
+            http_request.request_method = "GET",
+            http_request.status = 200,
+            labels.env = "production",
+            user_id = 42,
+            is_admin = false,
+            "hello!"
+        )
+    });
+
+    let output = String::from_utf8(output).expect("Entries aren't valid UTF-8");
+
+    let entry = LogEntry {
+        source_location: Some(LogEntrySourceLocation {
+            file: file!().into(),
+            line: Some(line),
+            function: None,
+        }),
+        ..expected_entry()
+    };
+
+    // entries are formatted through LogEntry, so they match byte-for-byte (including key order)
+    assert_eq!(
+        without_time(output.trim_end()),
+        serde_json::to_string(&entry).unwrap()
+    );
+}
+
+#[test]
+fn matches_entries_formatted_from_fields() {
+    let layer = tracing_stackdriver::layer::<Registry>().with_project_id("my-project");
+
+    let output = layer
+        .format_fields(LogSeverity::Info, "hello!", recorded_fields(), None)
+        .expect("Error formatting fields");
+
+    assert_eq!(
+        without_time(&output),
+        serde_json::to_string(&expected_entry()).unwrap()
+    );
+}
+
+#[cfg(all(feature = "opentelemetry", feature = "testing"))]
+#[test]
+fn matches_cloud_trace_fields_of_emitted_entries() {
+    use opentelemetry::trace::{SpanId, TraceId};
+    use tracing_stackdriver::CloudTraceConfiguration;
+
+    let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+    let span_id = SpanId::from_hex("00f067aa0ba902b7").unwrap();
+
+    let event = tracing_stackdriver::testing::format_with_trace_context(
+        CloudTraceConfiguration {
+            project_id: "my-project".to_owned(),
+        },
+        trace_id,
+        span_id,
+        true,
+    );
+
+    let entry = LogEntry {
+        severity: LogSeverity::Info,
+        message: Some("test event".into()),
+        trace: Some(format!("projects/my-project/traces/{trace_id}")),
+        span_id: event["logging.googleapis.com/spanId"]
+            .as_str()
+            .map(str::to_owned),
+        trace_sampled: true,
+        ..Default::default()
+    };
+
+    // the remaining fields of the entry (e.g. its span) have no dedicated LogEntry fields
+    let Value::Object(entry) = serde_json::to_value(&entry).unwrap() else {
+        panic!("LogEntry isn't serialized as an object");
+    };
+
+    assert!(entry.contains_key("logging.googleapis.com/spanId"));
+
+    for (key, value) in entry {
+        assert_eq!(event.get(&key), Some(&value), "Mismatched {key} field");
+    }
+}
@@ -0,0 +1,21 @@
+use helpers::run_with_tracing_layer;
+use serde_json::{json, Map, Value};
+use tracing_stackdriver::MessageSource;
+
+mod helpers;
+
+#[test]
+fn takes_message_from_configured_field() {
+    let layer =
+        tracing_stackdriver::layer().with_message_source(MessageSource::Field("msg".into()));
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(msg = "from a field", user = "someone");
+        tracing::info!("formatted message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].get("message"), Some(&json!("from a field")));
+    assert_eq!(events[0].get("msg"), None);
+    assert_eq!(events[1].get("message"), Some(&json!("formatted message")));
+}
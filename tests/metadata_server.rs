@@ -0,0 +1,58 @@
+#![cfg(feature = "opentelemetry")]
+//! The metadata server host is read from the `GCE_METADATA_HOST` environment variable, which this
+//! binary sets for the whole process. Tests run concurrently on separate threads, so this binary
+//! must only ever contain this single test.
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread,
+};
+use tracing_stackdriver::CloudTraceConfiguration;
+
+static PROJECT_ID: &str = "my_project_123";
+
+#[test]
+fn reads_project_id_from_metadata_server() {
+    // mock the metadata endpoint on a local port
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding mock metadata server");
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("Error accepting connection");
+        let mut reader = BufReader::new(stream);
+        let mut request = vec![];
+
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+
+            if line == "\r\n" || line.is_empty() {
+                break;
+            }
+
+            request.push(line);
+        }
+
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/text\r\nContent-Length: {}\r\n\r\n{}",
+            PROJECT_ID.len(),
+            PROJECT_ID
+        )
+        .unwrap();
+
+        request
+    });
+
+    std::env::set_var("GCE_METADATA_HOST", address.to_string());
+
+    let configuration = CloudTraceConfiguration::from_metadata_server()
+        .expect("Error reading from mock metadata server");
+
+    let request = server.join().unwrap();
+    assert_eq!(configuration.project_id, PROJECT_ID);
+    assert!(request[0].starts_with("GET /computeMetadata/v1/project/project-id "));
+    assert!(request
+        .iter()
+        .any(|line| line == "Metadata-Flavor: Google\r\n"));
+}
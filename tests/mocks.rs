@@ -6,6 +6,7 @@ use time::OffsetDateTime;
 pub struct MockSourceLocation {
     pub file: String,
     pub line: String,
+    pub function: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Debug)]
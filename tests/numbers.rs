@@ -0,0 +1,70 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use serde_json::{json, Map, Value};
+use tracing_stackdriver::FloatFormat;
+
+mod helpers;
+
+#[test]
+fn records_float_fields_as_numbers() {
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing::info!(
+            ratio = 1.5,
+            whole = 2.0,
+            negative = -0.25,
+            not_a_number = f64::NAN,
+            infinite = f64::INFINITY,
+            "hello!"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+
+    assert_eq!(event["ratio"], json!(1.5));
+    assert_eq!(event["whole"], json!(2.0));
+    assert_eq!(event["negative"], json!(-0.25));
+
+    // non-finite floats have no JSON representation
+    assert_eq!(event["notANumber"], "NaN");
+    assert_eq!(event["infinite"], "inf");
+}
+
+#[test]
+fn serializes_128_bit_integers_as_strings() {
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing::info!(
+            request_id = u128::MAX,
+            offset = i128::MIN,
+            "some stackdriver message"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.get("requestId"), Some(&json!(u128::MAX.to_string())));
+    assert_eq!(event.get("offset"), Some(&json!(i128::MIN.to_string())));
+}
+
+#[test]
+fn formats_floats_with_fixed_decimals() {
+    let emit = || {
+        tracing::info!(
+            tiny = 1e-9,
+            ratio = 0.5,
+            count = 3,
+            "some stackdriver message"
+        )
+    };
+
+    let default =
+        run_with_tracing::<Map<String, Value>>(emit).expect("Error converting test buffer to JSON");
+
+    let layer = tracing_stackdriver::layer().with_float_format(FloatFormat::FixedDecimal(9));
+    let fixed = run_with_tracing_layer::<Map<String, Value>>(layer, emit)
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(default[0]["tiny"], json!(1e-9));
+    assert_eq!(fixed[0]["tiny"], "0.000000001");
+    assert_eq!(fixed[0]["ratio"], "0.500000000");
+    assert_eq!(fixed[0]["count"], 3);
+}
@@ -12,8 +12,8 @@ use std::{
     fmt::Debug,
     sync::{Arc, Mutex},
 };
-use tracing_stackdriver::CloudTraceConfiguration;
-use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt};
+use tracing_stackdriver::{CloudTraceConfiguration, Layer};
+use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, Registry};
 
 mod helpers;
 mod mocks;
@@ -52,9 +52,35 @@ where
     SpanId::from_hex(hex).map_err(D::Error::custom)
 }
 
+/// Subscriber that the Stackdriver layer is registered with in these tests
+type OtelSubscriber = tracing_subscriber::layer::Layered<
+    tracing_opentelemetry::OpenTelemetryLayer<Registry, opentelemetry_sdk::trace::Tracer>,
+    Registry,
+>;
+
 fn test_with_tracing<M>(span_id: SpanId, trace_id: TraceId, make_writer: M, callback: impl FnOnce())
 where
     M: for<'writer> MakeWriter<'writer> + Sync + Send + 'static,
+{
+    test_with_layer(
+        |layer| layer,
+        span_id,
+        trace_id,
+        TraceState::default(),
+        make_writer,
+        callback,
+    )
+}
+
+fn test_with_layer<M>(
+    configure: impl FnOnce(Layer<OtelSubscriber>) -> Layer<OtelSubscriber>,
+    span_id: SpanId,
+    trace_id: TraceId,
+    trace_state: TraceState,
+    make_writer: M,
+    callback: impl FnOnce(),
+) where
+    M: for<'writer> MakeWriter<'writer> + Sync + Send + 'static,
 {
     use opentelemetry::trace::TracerProvider as _;
 
@@ -68,7 +94,7 @@ where
                 .with_tracer(TRACER.tracer("test")),
         )
         .with(
-            tracing_stackdriver::layer()
+            configure(tracing_stackdriver::layer())
                 .with_writer(make_writer)
                 .with_cloud_trace(CLOUD_TRACE_CONFIGURATION.clone()),
         );
@@ -79,7 +105,7 @@ where
         span_id,
         TraceFlags::default(),
         false,
-        TraceState::default(),
+        trace_state,
     )));
 
     // attach the tracing context
@@ -189,3 +215,313 @@ fn handles_nested_spans() {
         assert!(!message.trace_sampled)
     }
 }
+
+#[test]
+fn formats_trace_with_custom_template() {
+    // generate the output buffer
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let make_writer = move || MockWriter(shared.clone());
+
+    // generate relevant IDs
+    let mut rng = rand::thread_rng();
+    let span_id = SpanId::from_u64(rng.gen());
+    let trace_id = TraceId::from_u128(rng.gen());
+
+    // generate a tracing-based event
+    test_with_layer(
+        |layer| {
+            layer
+                .with_trace_template("traces/{trace_id}?project={project_id}")
+                .expect("Error validating trace template")
+        },
+        span_id,
+        trace_id,
+        TraceState::default(),
+        make_writer,
+        || {
+            let root = tracing::debug_span!("root");
+            let _root = root.enter();
+            tracing::debug!("test event");
+        },
+    );
+
+    let output: MockEventWithCloudTraceFields = serde_json::from_slice(&buffer.try_lock().unwrap())
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        output.trace_id,
+        format!("traces/{trace_id}?project={PROJECT_ID}"),
+        "Trace IDs do not match the custom template",
+    );
+}
+
+#[test]
+fn rejects_trace_templates_without_placeholders() {
+    assert!(tracing_stackdriver::layer::<Registry>()
+        .with_trace_template("traces/{trace_id}")
+        .is_err());
+
+    assert!(tracing_stackdriver::layer::<Registry>()
+        .with_trace_template("projects/{project_id}")
+        .is_err());
+}
+
+#[test]
+fn includes_cloud_trace_fields_for_current_and_explicit_parent_spans() {
+    // generate the output buffer
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let make_writer = move || MockWriter(shared.clone());
+
+    // generate relevant IDs
+    let mut rng = rand::thread_rng();
+    let span_id = SpanId::from_u64(rng.gen());
+    let trace_id = TraceId::from_u128(rng.gen());
+
+    // log one event within the current span, and another with an explicit parent
+    test_with_tracing(span_id, trace_id, make_writer, || {
+        let parent = tracing::debug_span!("parent");
+
+        parent.in_scope(|| tracing::debug!("current span event"));
+        tracing::debug!(parent: &parent, "explicit parent event");
+    });
+
+    let raw = &buffer.try_lock().unwrap();
+
+    let messages = raw
+        .split(|byte| byte == &b'\n')
+        .filter(|segment| !segment.is_empty())
+        .map(serde_json::from_slice)
+        .collect::<Result<Vec<MockEventWithCloudTraceFields>, _>>()
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(messages.len(), 2);
+
+    for message in messages {
+        assert_eq!(
+            message.trace_id,
+            format!("projects/{PROJECT_ID}/traces/{trace_id}"),
+            "Trace IDs are not compatible",
+        );
+    }
+}
+
+#[test]
+fn includes_cloud_trace_fields_when_explicit_parent_is_missing() {
+    // generate the output buffer
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let make_writer = move || MockWriter(shared.clone());
+
+    // generate relevant IDs
+    let mut rng = rand::thread_rng();
+    let span_id = SpanId::from_u64(rng.gen());
+    let trace_id = TraceId::from_u128(rng.gen());
+
+    // log an event whose explicit parent can't be looked up (e.g. because it already closed)
+    // from within a span that carries the trace ID
+    test_with_tracing(span_id, trace_id, make_writer, || {
+        let root = tracing::debug_span!("root");
+        let _root = root.enter();
+        let missing_parent = tracing::Id::from_u64(u64::MAX);
+        tracing::debug!(parent: missing_parent, "orphaned event");
+    });
+
+    let output: MockEventWithCloudTraceFields = serde_json::from_slice(&buffer.try_lock().unwrap())
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        output.trace_id,
+        format!("projects/{PROJECT_ID}/traces/{trace_id}"),
+        "Trace IDs are not compatible",
+    );
+}
+
+#[test]
+fn includes_trace_state_labels() {
+    #[derive(Deserialize)]
+    struct MockEventWithLabels {
+        #[serde(rename = "logging.googleapis.com/labels", default)]
+        labels: std::collections::BTreeMap<String, String>,
+    }
+
+    let mut rng = rand::thread_rng();
+    let span_id = SpanId::from_u64(rng.gen());
+    let trace_id = TraceId::from_u128(rng.gen());
+    let trace_state =
+        TraceState::from_key_value([("vendor", "value")]).expect("Error generating trace state");
+
+    let mut outputs = vec![];
+
+    for include_trace_state in [true, false] {
+        let buffer = Arc::new(Mutex::new(vec![]));
+        let shared = buffer.clone();
+        let make_writer = move || MockWriter(shared.clone());
+
+        test_with_layer(
+            |layer| layer.with_trace_state(include_trace_state),
+            span_id,
+            trace_id,
+            trace_state.clone(),
+            make_writer,
+            || {
+                let root = tracing::debug_span!("root");
+                let _root = root.enter();
+                tracing::debug!("test event");
+            },
+        );
+
+        let output: MockEventWithLabels = serde_json::from_slice(&buffer.try_lock().unwrap())
+            .expect("Error converting test buffer to JSON");
+
+        outputs.push(output.labels.get("tracestate").cloned());
+    }
+
+    assert_eq!(outputs, [Some("vendor=value".to_owned()), None]);
+}
+
+#[test]
+fn includes_span_kind_and_status_labels() {
+    #[derive(Deserialize)]
+    struct MockEventWithLabels {
+        #[serde(rename = "logging.googleapis.com/labels", default)]
+        labels: std::collections::BTreeMap<String, String>,
+    }
+
+    let mut rng = rand::thread_rng();
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let make_writer = move || MockWriter(shared.clone());
+
+    test_with_layer(
+        |layer| layer.with_span_kind_and_status(true),
+        SpanId::from_u64(rng.gen()),
+        TraceId::from_u128(rng.gen()),
+        TraceState::default(),
+        make_writer,
+        || {
+            let root =
+                tracing::info_span!("root", otel.kind = "server", otel.status_code = "error");
+            let _root = root.enter();
+            tracing::info!("test event");
+        },
+    );
+
+    let output: MockEventWithLabels = serde_json::from_slice(&buffer.try_lock().unwrap())
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        output.labels.get("spanKind").map(String::as_str),
+        Some("server")
+    );
+    assert_eq!(
+        output.labels.get("spanStatus").map(String::as_str),
+        Some("error")
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn formats_trace_without_a_tracer_provider() {
+    let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+    let span_id = SpanId::from_hex("00f067aa0ba902b7").unwrap();
+
+    let entry = tracing_stackdriver::testing::format_with_trace_context(
+        CLOUD_TRACE_CONFIGURATION.clone(),
+        trace_id,
+        span_id,
+        true,
+    );
+
+    assert_eq!(
+        entry["logging.googleapis.com/trace"],
+        "projects/my_project_123/traces/4bf92f3577b34da6a3ce929d0e0e4736"
+    );
+    assert_eq!(entry["logging.googleapis.com/trace_sampled"], true);
+}
+
+#[test]
+fn builds_preconfigured_subscribers() {
+    use opentelemetry::trace::TracerProvider as _;
+
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let make_writer = move || MockWriter(shared.clone());
+
+    let subscriber = tracing_stackdriver::opentelemetry::subscriber_with_writer(
+        TRACER.tracer("test"),
+        CLOUD_TRACE_CONFIGURATION.clone(),
+        make_writer,
+    );
+
+    tracing::subscriber::with_default(subscriber, || {
+        let root = tracing::info_span!("root");
+        let _root = root.enter();
+        tracing::info!("test event");
+    });
+
+    let output: MockEventWithCloudTraceFields = serde_json::from_slice(&buffer.try_lock().unwrap())
+        .expect("Error converting test buffer to JSON");
+
+    assert!(output
+        .trace_id
+        .starts_with(&format!("projects/{PROJECT_ID}/traces/")));
+    assert_ne!(output.span_id, SpanId::INVALID);
+}
+
+#[test]
+fn includes_cloud_trace_fields_from_ancestor_spans() {
+    use opentelemetry::trace::TracerProvider as _;
+    use tracing_subscriber::{filter::filter_fn, Layer};
+
+    // generate the output buffer
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let make_writer = move || MockWriter(shared.clone());
+
+    // generate relevant IDs
+    let mut rng = rand::thread_rng();
+    let span_id = SpanId::from_u64(rng.gen());
+    let trace_id = TraceId::from_u128(rng.gen());
+
+    // spans named "plain" are never seen by the OpenTelemetry layer, so they have no OtelData
+    let subscriber = tracing_subscriber::registry()
+        .with(
+            tracing_opentelemetry::layer()
+                .with_tracer(TRACER.tracer("test"))
+                .with_filter(filter_fn(|metadata| metadata.name() != "plain")),
+        )
+        .with(
+            tracing_stackdriver::layer()
+                .with_writer(make_writer)
+                .with_cloud_trace(CLOUD_TRACE_CONFIGURATION.clone()),
+        );
+
+    let context = opentelemetry::Context::current_with_span(TestSpan(SpanContext::new(
+        trace_id,
+        span_id,
+        TraceFlags::default(),
+        false,
+        TraceState::default(),
+    )));
+
+    let _context = context.attach();
+
+    tracing::subscriber::with_default(subscriber, || {
+        let root = tracing::info_span!("root");
+        let _root = root.enter();
+        let plain = tracing::info_span!("plain");
+        let _plain = plain.enter();
+        tracing::info!("plain event");
+    });
+
+    let output: MockEventWithCloudTraceFields = serde_json::from_slice(&buffer.try_lock().unwrap())
+        .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        output.trace_id,
+        format!("projects/{PROJECT_ID}/traces/{trace_id}"),
+        "Trace IDs are not compatible",
+    );
+}
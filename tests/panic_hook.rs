@@ -0,0 +1,80 @@
+use helpers::MockWriter;
+use std::sync::{Arc, Mutex, Once};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+mod helpers;
+
+/// Names of the threads whose panics reached the hook installed before the Stackdriver hook
+static PREVIOUS_HOOK_THREADS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Installs a recording hook followed by the Stackdriver hook, once for every test in this file
+fn install_hooks() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        std::panic::set_hook(Box::new(|_| {
+            let thread = std::thread::current().name().unwrap_or_default().to_owned();
+            PREVIOUS_HOOK_THREADS.lock().unwrap().push(thread);
+        }));
+
+        tracing_stackdriver::install_panic_hook();
+    });
+}
+
+/// Panics on a new thread with the given name
+fn panic_on_thread(name: &str, callback: impl FnOnce() + Send + 'static) {
+    let result = std::thread::Builder::new()
+        .name(name.to_owned())
+        .spawn(callback)
+        .expect("Error spawning thread")
+        .join();
+
+    assert!(result.is_err());
+}
+
+fn reached_previous_hook(name: &str) -> bool {
+    PREVIOUS_HOOK_THREADS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|thread| thread == name)
+}
+
+#[test]
+fn reports_panics_as_errors() {
+    install_hooks();
+
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let shared = buffer.clone();
+    let layer = tracing_stackdriver::layer().with_writer(move || MockWriter(shared.clone()));
+    let subscriber = Registry::default().with(layer);
+
+    panic_on_thread("panicking", || {
+        tracing::subscriber::with_default(subscriber, || panic!("boom"))
+    });
+
+    let buffer = buffer
+        .lock()
+        .expect("Couldn't get lock on test write target");
+    let entry: serde_json::Value =
+        serde_json::from_slice(&buffer).expect("Error converting test buffer to JSON");
+
+    let message = entry["message"].as_str().expect("Missing message");
+    assert!(message.starts_with("thread 'panicking' panicked at tests/panic_hook.rs:"));
+    assert!(message.contains("boom"));
+    assert_eq!(entry["severity"], "ERROR");
+    assert_eq!(
+        entry["@type"],
+        "type.googleapis.com/google.devtools.clouderrorreporting.v1beta1.ReportedErrorEvent"
+    );
+    assert!(reached_previous_hook("panicking"));
+}
+
+#[test]
+fn chains_to_previous_hook_without_a_subscriber() {
+    install_hooks();
+
+    panic_on_thread("unsubscribed", || panic!("boom"));
+
+    assert!(reached_previous_hook("unsubscribed"));
+}
@@ -0,0 +1,132 @@
+use helpers::run_with_tracing_layer;
+use serde_json::{json, Map, Value};
+use tracing_stackdriver::Platform;
+
+mod helpers;
+
+#[test]
+fn formats_trace_ids_with_project_id() {
+    let layer = tracing_stackdriver::layer().with_project_id("my_project_123");
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(trace_id = "event_trace", "event trace");
+
+        let span = tracing::info_span!("request", trace_id = "span_trace");
+        let _span = span.enter();
+        tracing::info!("span trace");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let traces: Vec<_> = events
+        .iter()
+        .map(|event| event["logging.googleapis.com/trace"].clone())
+        .collect();
+
+    assert_eq!(
+        traces,
+        [
+            json!("projects/my_project_123/traces/event_trace"),
+            json!("projects/my_project_123/traces/span_trace"),
+        ]
+    );
+    assert!(!events[0].contains_key("traceId"));
+}
+
+#[test]
+fn formats_trace_ids_with_trace_template() {
+    let layer = tracing_stackdriver::layer()
+        .with_project_id("my_project_123")
+        .with_trace_template("traces/{trace_id}?project={project_id}")
+        .expect("Error validating trace template");
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(trace_id = "event_trace", "event trace")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        events[0]["logging.googleapis.com/trace"],
+        json!("traces/event_trace?project=my_project_123")
+    );
+}
+
+#[test]
+fn parses_cloud_trace_context_trace_ids() {
+    let layer = tracing_stackdriver::layer()
+        .with_project_id("my_project_123")
+        .with_cloud_trace_context(true);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        let span = tracing::info_span!("request", trace_id = "span_trace/255;o=0");
+        let _guard = span.enter();
+        tracing::info!("span trace");
+        tracing::info!(trace_id = "event_trace", "event trace");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        events[0]["logging.googleapis.com/trace"],
+        json!("projects/my_project_123/traces/span_trace")
+    );
+    assert_eq!(
+        events[0]["logging.googleapis.com/spanId"],
+        json!("00000000000000ff")
+    );
+    assert!(!events[0].contains_key("logging.googleapis.com/trace_sampled"));
+    assert_eq!(
+        events[1]["logging.googleapis.com/trace"],
+        json!("projects/my_project_123/traces/event_trace")
+    );
+}
+
+#[test]
+fn keeps_trace_ids_without_project_id() {
+    let events = run_with_tracing_layer::<Map<String, Value>>(tracing_stackdriver::layer(), || {
+        tracing::info!(trace_id = "event_trace", "event trace")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["traceId"], "event_trace");
+    assert!(!event.contains_key("logging.googleapis.com/trace"));
+}
+
+#[test]
+fn includes_project_id_in_resource() {
+    let layer = tracing_stackdriver::layer()
+        .with_platform(Platform::Generic)
+        .with_project_id("my_project_123");
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!("some stackdriver message")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event["resource"],
+        json!({ "type": "global", "labels": { "project_id": "my_project_123" } })
+    );
+}
+
+#[test]
+fn formats_targets_as_log_names() {
+    let layer = tracing_stackdriver::layer()
+        .with_project_id("my_project_123")
+        .with_target_as_log_name(true);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(target: "my_crate::module", "module log");
+        tracing::info!(target: "my crate/with:odd chars", "odd log");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        events[0]["logName"],
+        json!("projects/my_project_123/logs/my_crate.module")
+    );
+    assert_eq!(
+        events[1]["logName"],
+        json!("projects/my_project_123/logs/my_crate%2Fwith_odd_chars")
+    );
+}
@@ -0,0 +1,41 @@
+use helpers::run_with_tracing;
+use serde_json::{json, Map, Value};
+
+mod helpers;
+
+#[test]
+fn merges_raw_json_objects_verbatim() {
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing::info!(
+            __raw_json = r#"{"snake_key": 1, "nested": {"inner_key": true}}"#,
+            "some stackdriver message"
+        );
+        tracing::info!(__raw_json = "{not json", "another stackdriver message");
+        tracing::info!(__raw_json = "[1, 2]", "a third stackdriver message");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.get("snake_key"), Some(&json!(1)));
+    assert_eq!(event.get("nested"), Some(&json!({ "inner_key": true })));
+    assert!(!event.contains_key("__raw_json"));
+    assert!(!event.contains_key("RawJson"));
+
+    let invalid_event = events.get(1).expect("No invalid event heard");
+    assert_eq!(
+        invalid_event.get("message"),
+        Some(&json!("another stackdriver message"))
+    );
+    assert!(!invalid_event.contains_key("__raw_json"));
+
+    // dropped fields are reported within the entry itself
+    let invalid_error = invalid_event["rawJsonError"].as_str().unwrap();
+    assert!(invalid_error.starts_with("key must be a string"));
+
+    let non_object_event = events.get(2).expect("No non-object event heard");
+    assert_eq!(
+        non_object_event.get("rawJsonError"),
+        Some(&json!("expected a JSON object"))
+    );
+    assert!(!event.contains_key("rawJsonError"));
+}
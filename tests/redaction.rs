@@ -0,0 +1,170 @@
+use helpers::run_with_tracing_layer;
+use serde_json::{json, Map, Value};
+
+mod helpers;
+mod mocks;
+
+#[test]
+fn redacts_top_level_fields() {
+    let layer = tracing_stackdriver::layer().with_redacted_fields(&["password", "user_ssn"]);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(
+            password = "hunter2",
+            user_ssn = "123-45-6789",
+            username = "someone",
+            "logging in"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.get("password"), Some(&json!("[REDACTED]")));
+    assert_eq!(event.get("userSsn"), Some(&json!("[REDACTED]")));
+    assert_eq!(event.get("username"), Some(&json!("someone")));
+}
+
+#[test]
+fn redacts_http_request_fields() {
+    let layer = tracing_stackdriver::layer().with_redacted_fields(&["authorization"]);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(
+            http_request.request_method = "GET",
+            http_request.authorization = "Bearer secret",
+            "request received"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.get("httpRequest"),
+        Some(&json!({
+            "requestMethod": "GET",
+            "authorization": "[REDACTED]",
+        }))
+    );
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn redacts_fields_matching_patterns() {
+    let pattern = regex::Regex::new(".*token.*").unwrap();
+    let layer = tracing_stackdriver::layer().with_redaction_pattern(pattern);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(
+            access_token = "abc",
+            token_expiry = 3600,
+            labels.refresh_token = "def",
+            http_request.csrf_token = "ghi",
+            http_request.request_method = "POST",
+            user = "someone",
+            "refreshing tokens"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.get("accessToken"), Some(&json!("[REDACTED]")));
+    assert_eq!(event.get("tokenExpiry"), Some(&json!("[REDACTED]")));
+    assert_eq!(event.get("user"), Some(&json!("someone")));
+    assert_eq!(
+        event["logging.googleapis.com/labels"]["refreshToken"],
+        json!("[REDACTED]")
+    );
+    assert_eq!(event["httpRequest"]["csrfToken"], json!("[REDACTED]"));
+    assert_eq!(event["httpRequest"]["requestMethod"], json!("POST"));
+}
+
+#[test]
+fn redacts_flattened_span_fields() {
+    let layer = tracing_stackdriver::layer()
+        .with_flatten_single_field_spans(true)
+        .with_redacted_fields(&["api_token"]);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        let span = tracing::info_span!("authenticated", api_token = "secret");
+        let _span = span.enter();
+        tracing::info!("inside of a span");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.get("apiToken"), Some(&json!("[REDACTED]")));
+}
+
+#[test]
+fn redacts_span_fields() {
+    let layer = tracing_stackdriver::layer()
+        .with_span_fields_by_name(true)
+        .with_redacted_fields(&["password", "session"]);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        let span = tracing::info_span!(
+            "login",
+            password = "hunter2",
+            labels.session = "abc123",
+            username = "someone"
+        );
+        let _span = span.enter();
+        tracing::info!(password = "hunter2", "logging in");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["password"], json!("[REDACTED]"));
+    assert_eq!(event["span"]["password"], json!("[REDACTED]"));
+    assert_eq!(event["span"]["username"], json!("someone"));
+    assert_eq!(event["spans"][0]["password"], json!("[REDACTED]"));
+    assert_eq!(
+        event["spanFields"]["login"]["password"],
+        json!("[REDACTED]")
+    );
+    assert_eq!(
+        event["logging.googleapis.com/labels"]["session"],
+        json!("[REDACTED]")
+    );
+    assert!(!serde_json::to_string(event).unwrap().contains("hunter2"));
+}
+
+#[test]
+fn redacts_promoted_span_fields() {
+    let layer = tracing_stackdriver::layer()
+        .with_flattened_span_fields(true)
+        .with_span_field_prefix(Some("span"))
+        .with_redacted_fields(&["password"]);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        let span = tracing::info_span!("login", password = "hunter2", username = "someone");
+        let _span = span.enter();
+        tracing::info!("logging in");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["spanPassword"], json!("[REDACTED]"));
+    assert_eq!(event["spanUsername"], json!("someone"));
+    assert!(!serde_json::to_string(event).unwrap().contains("hunter2"));
+}
+
+#[test]
+fn redacts_span_fields_of_formatted_fields() {
+    let layer = tracing_stackdriver::layer::<tracing_subscriber::Registry>()
+        .with_redacted_fields(&["password"]);
+    let mut span_fields = Map::new();
+    span_fields.insert("password".to_owned(), json!("hunter2"));
+
+    let entry = layer
+        .format_fields(
+            tracing_stackdriver::LogSeverity::Info,
+            "logging in",
+            Map::new(),
+            Some(("login", span_fields)),
+        )
+        .expect("Error formatting fields");
+
+    let entry: Value = serde_json::from_str(&entry).expect("Invalid JSON entry");
+    assert_eq!(entry["span"]["password"], json!("[REDACTED]"));
+}
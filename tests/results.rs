@@ -0,0 +1,35 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use serde_json::{json, Map, Value};
+
+mod helpers;
+
+#[test]
+fn formats_results_as_objects() {
+    let layer = tracing_stackdriver::layer().with_result_objects(true);
+    let ok: Result<u16, String> = Ok(200);
+    let err: Result<u16, String> = Err("not found".into());
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(result = ?ok, "ok result");
+        tracing::info!(result = ?err, "err result");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].get("result"), Some(&json!({ "ok": 200 })));
+    assert_eq!(
+        events[1].get("result"),
+        Some(&json!({ "err": "not found" }))
+    );
+}
+
+#[test]
+fn formats_results_as_strings_by_default() {
+    let ok: Result<u16, String> = Ok(200);
+
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing::info!(result = ?ok, "ok result");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0].get("result"), Some(&json!("Ok(200)")));
+}
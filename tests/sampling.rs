@@ -0,0 +1,48 @@
+use helpers::run_with_tracing_layer;
+use serde_json::{Map, Value};
+
+mod helpers;
+
+#[test]
+fn samples_debug_events() {
+    let layer = tracing_stackdriver::layer().with_debug_sampling(0.25);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        for _ in 0..10_000 {
+            tracing::debug!("debug event");
+            tracing::trace!("trace event");
+        }
+
+        for _ in 0..100 {
+            tracing::info!("info event");
+        }
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let count = |message: &str| {
+        events
+            .iter()
+            .filter(|event| event["message"] == message)
+            .count()
+    };
+
+    let kept = (count("debug event") + count("trace event")) as f64 / 20_000.0;
+
+    assert!(
+        (0.22..=0.28).contains(&kept),
+        "Kept fraction {kept} is not within tolerance"
+    );
+    assert_eq!(count("info event"), 100);
+}
+
+#[test]
+fn keeps_all_debug_events_by_default() {
+    let events = run_with_tracing_layer::<Map<String, Value>>(tracing_stackdriver::layer(), || {
+        for _ in 0..100 {
+            tracing::debug!("debug event");
+        }
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 100);
+}
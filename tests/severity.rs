@@ -0,0 +1,314 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use mocks::MockDefaultEvent;
+use serde_json::{Map, Value};
+use tracing_core::LevelFilter;
+use tracing_stackdriver::LogSeverity;
+
+mod helpers;
+mod mocks;
+
+#[test]
+fn converts_level_filters_to_severities() {
+    let cases = [
+        (LevelFilter::OFF, LogSeverity::Emergency),
+        (LevelFilter::TRACE, LogSeverity::Debug),
+        (LevelFilter::DEBUG, LogSeverity::Debug),
+        (LevelFilter::INFO, LogSeverity::Info),
+        (LevelFilter::WARN, LogSeverity::Warning),
+        (LevelFilter::ERROR, LogSeverity::Error),
+    ];
+
+    for (level_filter, severity) in cases {
+        assert_eq!(LogSeverity::from(level_filter), severity);
+    }
+}
+
+#[test]
+fn converts_severities_to_level_filters() {
+    let cases = [
+        (LogSeverity::Default, LevelFilter::TRACE),
+        (LogSeverity::Debug, LevelFilter::DEBUG),
+        (LogSeverity::Info, LevelFilter::INFO),
+        (LogSeverity::Notice, LevelFilter::INFO),
+        (LogSeverity::Warning, LevelFilter::WARN),
+        (LogSeverity::Error, LevelFilter::ERROR),
+        (LogSeverity::Critical, LevelFilter::ERROR),
+        (LogSeverity::Alert, LevelFilter::ERROR),
+        (LogSeverity::Emergency, LevelFilter::ERROR),
+    ];
+
+    for (severity, level_filter) in cases {
+        assert_eq!(severity.to_level_filter(), level_filter);
+    }
+}
+
+#[test]
+fn round_trips_level_filters() {
+    for level_filter in [
+        LevelFilter::DEBUG,
+        LevelFilter::INFO,
+        LevelFilter::WARN,
+        LevelFilter::ERROR,
+    ] {
+        assert_eq!(
+            LogSeverity::from(level_filter).to_level_filter(),
+            level_filter
+        );
+    }
+}
+
+#[test]
+fn promotes_events_within_min_severity_spans() {
+    let events = run_with_tracing::<MockDefaultEvent>(|| {
+        tracing::info!("outside of a span");
+        let span = tracing::info_span!("error_handling", min_severity = "warning");
+        let _span = span.enter();
+        tracing::info!("inside of a span");
+        tracing::error!("already more severe");
+        tracing::info!(severity = "critical", "explicitly more severe");
+        tracing::info!(severity = "debug", "explicitly less severe");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let severities: Vec<_> = events.iter().map(|event| event.severity.as_str()).collect();
+
+    assert_eq!(
+        severities,
+        ["INFO", "WARNING", "ERROR", "CRITICAL", "WARNING"]
+    );
+}
+
+#[test]
+fn applies_default_severity_to_undetermined_severities() {
+    let layer = tracing_stackdriver::layer().with_default_severity(LogSeverity::Info);
+
+    let events = run_with_tracing_layer::<MockDefaultEvent>(layer, || {
+        tracing::info!(severity = "unknown", "unrecognized severity");
+        tracing::debug!("leveled event");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let severities: Vec<_> = events.iter().map(|event| event.severity.as_str()).collect();
+
+    assert_eq!(severities, ["INFO", "DEBUG"]);
+}
+
+#[test]
+fn includes_numeric_severities() {
+    let layer = tracing_stackdriver::layer().with_numeric_severity(true);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!("info event");
+        tracing::error!("error event");
+        tracing::info!(severity = "notice", "notice event");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let severities: Vec<_> = events
+        .iter()
+        .map(|event| (event["severity"].clone(), event["severityNumber"].clone()))
+        .collect();
+
+    assert_eq!(
+        severities,
+        [
+            (Value::from("INFO"), Value::from(200)),
+            (Value::from("ERROR"), Value::from(500)),
+            (Value::from("NOTICE"), Value::from(300)),
+        ]
+    );
+}
+
+#[test]
+fn omits_numeric_severities_by_default() {
+    let events = run_with_tracing::<Map<String, Value>>(|| tracing::info!("info event"))
+        .expect("Error converting test buffer to JSON");
+
+    assert!(!events[0].contains_key("severityNumber"));
+}
+
+#[test]
+fn honors_stackdriver_event_severities() {
+    let severities = [
+        LogSeverity::Default,
+        LogSeverity::Debug,
+        LogSeverity::Info,
+        LogSeverity::Notice,
+        LogSeverity::Warning,
+        LogSeverity::Error,
+        LogSeverity::Critical,
+        LogSeverity::Alert,
+        LogSeverity::Emergency,
+    ];
+
+    let events = run_with_tracing::<MockDefaultEvent>(|| {
+        for severity in severities {
+            tracing_stackdriver::stackdriver_event!(severity, "typed severity");
+        }
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let actual: Vec<_> = events.iter().map(|event| event.severity.as_str()).collect();
+
+    assert_eq!(
+        actual,
+        [
+            "DEFAULT",
+            "DEBUG",
+            "INFO",
+            "NOTICE",
+            "WARNING",
+            "ERROR",
+            "CRITICAL",
+            "ALERT",
+            "EMERGENCY"
+        ]
+    );
+}
+
+#[test]
+fn includes_stackdriver_event_fields() {
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing_stackdriver::stackdriver_event!(
+            LogSeverity::Critical,
+            user_id = 42,
+            labels.region = "us-east1",
+            "typed severity"
+        );
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["severity"], "CRITICAL");
+    assert_eq!(event["message"], "typed severity");
+    assert_eq!(event["userId"], 42);
+    assert_eq!(event["logging.googleapis.com/labels"]["region"], "us-east1");
+}
+
+#[test]
+fn formats_stackdriver_event_messages() {
+    let attempt = 3;
+
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing_stackdriver::stackdriver_event!(LogSeverity::Notice, "attempt {attempt} of {}", 5);
+        tracing_stackdriver::stackdriver_event!(
+            LogSeverity::Alert,
+            user_id = 42,
+            "retrying {:?}",
+            "request"
+        );
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events[0]["severity"], "NOTICE");
+    assert_eq!(events[0]["message"], "attempt 3 of 5");
+    assert_eq!(events[1]["severity"], "ALERT");
+    assert_eq!(events[1]["message"], "retrying \"request\"");
+    assert_eq!(events[1]["userId"], 42);
+}
+
+#[test]
+fn parses_debug_formatted_severity_overrides() {
+    let severities = [
+        LogSeverity::Default,
+        LogSeverity::Debug,
+        LogSeverity::Info,
+        LogSeverity::Notice,
+        LogSeverity::Warning,
+        LogSeverity::Error,
+        LogSeverity::Critical,
+        LogSeverity::Alert,
+        LogSeverity::Emergency,
+    ];
+
+    let events = run_with_tracing::<MockDefaultEvent>(|| {
+        for severity in severities {
+            tracing::info!(severity = ?severity, "debug-formatted severity");
+        }
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let expected: Vec<_> = severities.iter().map(LogSeverity::to_string).collect();
+
+    let actual: Vec<_> = events.into_iter().map(|event| event.severity).collect();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parses_display_and_debug_forms_of_every_severity() {
+    use std::str::FromStr;
+
+    for severity in [
+        LogSeverity::Default,
+        LogSeverity::Debug,
+        LogSeverity::Info,
+        LogSeverity::Notice,
+        LogSeverity::Warning,
+        LogSeverity::Error,
+        LogSeverity::Critical,
+        LogSeverity::Alert,
+        LogSeverity::Emergency,
+    ] {
+        assert_eq!(LogSeverity::from_str(&severity.to_string()), Ok(severity));
+        assert_eq!(
+            LogSeverity::from_str(&format!("{severity:?}")),
+            Ok(severity)
+        );
+        assert_eq!(
+            LogSeverity::from(Value::from(format!("{severity:?}"))),
+            severity
+        );
+    }
+}
+
+// doctests are disabled for the library, so the `From<&Level>` example is mirrored here verbatim
+#[test]
+fn runs_level_conversion_doc_example() {
+    let events = run_with_tracing::<MockDefaultEvent>(|| {
+        use tracing::Level;
+        use tracing_stackdriver::LogSeverity;
+
+        let severity = LogSeverity::from(&Level::WARN);
+        tracing::info!(severity = %severity, "emitted as a WARNING");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.severity, "WARNING");
+}
+
+#[test]
+fn overrides_severity_with_level_derived_values() {
+    let events = run_with_tracing::<MockDefaultEvent>(|| {
+        tracing::info!(
+            severity = %LogSeverity::from(&tracing::Level::WARN),
+            "displayed level severity"
+        );
+        tracing::info!(
+            severity = ?LogSeverity::from(tracing::Level::ERROR),
+            "debug-formatted level severity"
+        );
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let actual: Vec<_> = events.into_iter().map(|event| event.severity).collect();
+
+    assert_eq!(actual, ["WARNING", "ERROR"]);
+}
+
+#[test]
+fn drops_muted_severities() {
+    let layer = tracing_stackdriver::layer().with_muted_severities(&[LogSeverity::Notice]);
+
+    let events = run_with_tracing_layer::<MockDefaultEvent>(layer, || {
+        tracing::info!(severity = "notice", "muted notice");
+        tracing::info!("kept info");
+        tracing::warn!("kept warning");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let severities: Vec<_> = events.iter().map(|event| event.severity.as_str()).collect();
+
+    assert_eq!(severities, ["INFO", "WARNING"]);
+}
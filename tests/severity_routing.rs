@@ -0,0 +1,60 @@
+use helpers::MockWriter;
+use serde_json::{Map, Value};
+use std::sync::{Arc, Mutex};
+use tracing_stackdriver::LogSeverity;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+mod helpers;
+
+fn messages(buffer: &Arc<Mutex<Vec<u8>>>) -> Vec<String> {
+    let buffer = buffer
+        .lock()
+        .expect("Couldn't get lock on test write target");
+
+    serde_json::Deserializer::from_slice(&buffer)
+        .into_iter::<Map<String, Value>>()
+        .map(|event| {
+            event.expect("Error converting test buffer to JSON")["message"]
+                .as_str()
+                .expect("No message")
+                .to_owned()
+        })
+        .collect()
+}
+
+#[test]
+fn routes_entries_by_severity() {
+    let debug_buffer = Arc::new(Mutex::new(vec![]));
+    let info_buffer = Arc::new(Mutex::new(vec![]));
+    let default_buffer = Arc::new(Mutex::new(vec![]));
+
+    let debug_shared = debug_buffer.clone();
+    let info_shared = info_buffer.clone();
+    let default_shared = default_buffer.clone();
+
+    let routes: Vec<(LogSeverity, Box<dyn Fn() -> MockWriter + Send + Sync>)> = vec![
+        (
+            LogSeverity::Info,
+            Box::new(move || MockWriter(info_shared.clone())),
+        ),
+        (
+            LogSeverity::Debug,
+            Box::new(move || MockWriter(debug_shared.clone())),
+        ),
+    ];
+
+    let stackdriver = tracing_stackdriver::layer()
+        .with_severity_routing(routes, move || MockWriter(default_shared.clone()));
+
+    let subscriber = Registry::default().with(stackdriver);
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::debug!("debug event");
+        tracing::info!("info event");
+        tracing::error!("error event");
+    });
+
+    assert_eq!(messages(&debug_buffer), ["debug event"]);
+    assert_eq!(messages(&info_buffer), ["info event", "error event"]);
+    assert!(messages(&default_buffer).is_empty());
+}
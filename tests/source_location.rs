@@ -19,6 +19,61 @@ fn includes_source_location() {
 fn excludes_source_location() {
     let layer = tracing_stackdriver::layer().with_source_location(false);
 
-    run_with_tracing_layer::<MockDefaultEvent>(layer, || tracing::info!("hello!"))
-        .expect_err("Failed to exclude source location fields from events");
+    let events =
+        run_with_tracing_layer::<serde_json::Map<String, serde_json::Value>>(layer, || {
+            tracing::info!("hello!")
+        })
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(event.contains_key("severity"));
+    assert!(!event.contains_key("logging.googleapis.com/sourceLocation"));
+}
+
+#[tracing::instrument]
+fn instrumented_function() {
+    tracing::info!("hello from an instrumented function!");
+}
+
+#[test]
+fn includes_source_function_from_span() {
+    let layer = tracing_stackdriver::layer().with_source_function_from_span(true);
+
+    let events = run_with_tracing_layer::<MockDefaultEvent>(layer, || {
+        tracing::info!("hello from outside of a span!");
+        instrumented_function();
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].source_location.function, None);
+    assert_eq!(
+        events[1].source_location.function.as_deref(),
+        Some("instrumented_function")
+    );
+}
+
+#[test]
+fn omits_source_function_by_default() {
+    let events = run_with_tracing::<MockDefaultEvent>(instrumented_function)
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.source_location.function, None);
+}
+
+#[test]
+fn includes_source_location_above_min_severity() {
+    let layer = tracing_stackdriver::layer()
+        .with_source_location_min_severity(tracing_stackdriver::LogSeverity::Warning);
+
+    let events =
+        run_with_tracing_layer::<serde_json::Map<String, serde_json::Value>>(layer, || {
+            tracing::info!("routine");
+            tracing::error!("failure");
+        })
+        .expect("Error converting test buffer to JSON");
+
+    assert!(!events[0].contains_key("logging.googleapis.com/sourceLocation"));
+    assert!(events[1].contains_key("logging.googleapis.com/sourceLocation"));
 }
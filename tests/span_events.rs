@@ -0,0 +1,67 @@
+use helpers::run_with_tracing_layer;
+use serde_json::{Map, Value};
+use tracing_stackdriver::LogSeverity;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+mod helpers;
+mod mocks;
+
+fn span_close_severities(
+    layer: tracing_stackdriver::Layer<tracing_subscriber::Registry>,
+) -> Vec<Value> {
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info_span!("work").in_scope(|| tracing::info!("working"));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["severity"], "INFO");
+    assert_eq!(events[1]["message"], "close");
+
+    events
+        .into_iter()
+        .skip(1)
+        .map(|event| event["severity"].clone())
+        .collect()
+}
+
+#[test]
+fn emits_span_events_as_debug_by_default() {
+    let layer = tracing_stackdriver::layer().with_span_events(FmtSpan::CLOSE);
+
+    assert_eq!(span_close_severities(layer), vec!["DEBUG"]);
+}
+
+#[test]
+fn emits_span_events_with_configured_severity() {
+    let layer = tracing_stackdriver::layer()
+        .with_span_events(FmtSpan::CLOSE)
+        .with_span_event_severity(LogSeverity::Notice);
+
+    assert_eq!(span_close_severities(layer), vec!["NOTICE"]);
+}
+
+#[test]
+fn emits_span_summaries_on_close() {
+    let layer = tracing_stackdriver::layer().with_span_summaries(true);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info_span!("work", job_id = 7).in_scope(|| tracing::info!("working"));
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+
+    let summary = &events[1];
+    assert_eq!(summary["message"], "work closed");
+    assert_eq!(summary["severity"], "DEBUG");
+    assert_eq!(summary["span"]["name"], "work");
+    assert_eq!(summary["span"]["job_id"], 7);
+
+    let elapsed = summary["elapsed"].as_str().expect("Missing elapsed field");
+    let seconds: f64 = elapsed
+        .strip_suffix('s')
+        .and_then(|seconds| seconds.parse().ok())
+        .expect("Invalid elapsed field");
+    assert!(seconds >= 0.0);
+}
@@ -0,0 +1,23 @@
+use helpers::run_with_tracing;
+use serde_json::{Map, Value};
+
+mod helpers;
+
+#[test]
+fn renames_backtraces_to_stack_traces() {
+    let backtrace = std::backtrace::Backtrace::force_capture();
+
+    let events = run_with_tracing::<Map<String, Value>>(
+        || tracing::error!(backtrace = %backtrace, "some stackdriver message"),
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    let stack_trace = event
+        .get("stackTrace")
+        .and_then(|stack_trace| stack_trace.as_str())
+        .expect("No stack trace");
+
+    assert!(!stack_trace.is_empty());
+    assert!(!event.contains_key("backtrace"));
+}
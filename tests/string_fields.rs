@@ -0,0 +1,56 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use serde_json::{json, Map, Value};
+
+mod helpers;
+
+#[test]
+fn truncates_long_string_fields() {
+    #[derive(Debug)]
+    struct Huge {
+        #[allow(dead_code)]
+        data: Vec<u8>,
+    }
+
+    let huge = Huge {
+        data: vec![0; 1024],
+    };
+
+    let layer = tracing_stackdriver::layer().with_max_field_string_len(16);
+    let events = run_with_tracing_layer::<Map<String, Value>>(
+        layer,
+        || tracing::info!(huge = ?huge, short = "short", "some stackdriver message"),
+    )
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["huge"], "Huge { data: [0,…");
+    assert_eq!(event["short"], "short");
+    assert_eq!(event["__truncated"], true);
+
+    let events = run_with_tracing::<Map<String, Value>>(
+        || tracing::info!(huge = ?huge, "some stackdriver message"),
+    )
+    .expect("Error converting test buffer to JSON");
+
+    assert!(!events[0].contains_key("__truncated"));
+}
+
+#[test]
+fn embeds_json_string_fields() {
+    let layer = tracing_stackdriver::layer().with_json_string_fields(&["payload", "invalid"]);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(
+            payload = r#"{"user": {"id": 42}}"#,
+            invalid = "{not json",
+            other = r#"{"kept": "as a string"}"#,
+            "some stackdriver message"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["payload"], json!({ "user": { "id": 42 } }));
+    assert_eq!(event["invalid"], "{not json");
+    assert_eq!(event["other"], r#"{"kept": "as a string"}"#);
+}
@@ -0,0 +1,195 @@
+use helpers::{run_with_tracing_layer, run_with_tracing_layer_raw};
+use serde_json::{json, Map, Value};
+use tracing_stackdriver::Platform;
+
+mod helpers;
+mod mocks;
+
+#[test]
+fn nests_custom_fields_under_json_payload() {
+    let layer = tracing_stackdriver::layer().with_structured_payload(true);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        let span = tracing::info_span!("stackdriver_span", foo = "bar");
+        let _guard = span.enter();
+        tracing::info!(custom_field = "value", labels.foo = "bar", "hello!")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    let payload = event
+        .get("jsonPayload")
+        .and_then(Value::as_object)
+        .expect("No jsonPayload found");
+
+    assert_eq!(event.get("severity"), Some(&json!("INFO")));
+    assert!(event.contains_key("time"));
+    assert!(event.contains_key("logging.googleapis.com/labels"));
+    assert!(!event.contains_key("customField"));
+    assert!(!event.contains_key("span"));
+    assert_eq!(payload.get("customField"), Some(&json!("value")));
+    assert_eq!(payload.get("message"), Some(&json!("hello!")));
+    assert_eq!(payload["span"]["name"], json!("stackdriver_span"));
+    assert!(!payload.contains_key("severity"));
+}
+
+#[test]
+fn keeps_flat_shape_by_default() {
+    let events = run_with_tracing_layer::<Map<String, Value>>(tracing_stackdriver::layer(), || {
+        tracing::info!(custom_field = "value", "hello!")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(!event.contains_key("jsonPayload"));
+    assert_eq!(event.get("customField"), Some(&json!("value")));
+}
+
+#[test]
+fn emits_a_single_severity() {
+    for structured_payload in [true, false] {
+        let layer = tracing_stackdriver::layer().with_structured_payload(structured_payload);
+
+        let output = run_with_tracing_layer_raw(layer, || {
+            tracing::info!(Severity = "warning", "hello!");
+        });
+
+        let output = String::from_utf8(output).expect("Invalid UTF-8 output");
+        assert_eq!(output.matches("\"severity\"").count(), 1, "{output}");
+
+        let event: Map<String, Value> =
+            serde_json::from_str(&output).expect("Error converting test buffer to JSON");
+
+        assert_eq!(event.get("severity"), Some(&json!("WARNING")));
+    }
+}
+
+const TRACE_CONTEXT: &str = "105445aa7843bc8bf206b12000100000/1;o=1";
+
+fn format_platform_entry(platform: Platform) -> Map<String, Value> {
+    let layer = tracing_stackdriver::layer()
+        .with_platform(platform)
+        .with_project_id("my-project-id");
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(trace_id = TRACE_CONTEXT, "hello!");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    events.into_iter().next().expect("No event heard")
+}
+
+#[test]
+fn formats_cloud_run_entries() {
+    let event = format_platform_entry(Platform::CloudRun);
+
+    assert_eq!(event["message"], "hello!");
+    assert_eq!(
+        event["resource"],
+        json!({ "type": "cloud_run_revision", "labels": { "project_id": "my-project-id" } })
+    );
+    assert_eq!(
+        event["logging.googleapis.com/trace"],
+        "projects/my-project-id/traces/105445aa7843bc8bf206b12000100000"
+    );
+    assert_eq!(event["logging.googleapis.com/spanId"], "0000000000000001");
+    assert_eq!(event["logging.googleapis.com/trace_sampled"], true);
+}
+
+#[test]
+fn formats_app_engine_entries() {
+    let event = format_platform_entry(Platform::AppEngine);
+
+    assert_eq!(event["message"], "hello!");
+    assert_eq!(
+        event["resource"],
+        json!({ "type": "gae_app", "labels": { "project_id": "my-project-id" } })
+    );
+    assert_eq!(
+        event["logging.googleapis.com/trace"],
+        "projects/my-project-id/traces/105445aa7843bc8bf206b12000100000"
+    );
+    assert_eq!(event["logging.googleapis.com/spanId"], "0000000000000001");
+    assert_eq!(event["logging.googleapis.com/trace_sampled"], true);
+}
+
+#[test]
+fn formats_gke_entries() {
+    let event = format_platform_entry(Platform::Gke);
+
+    assert_eq!(event["message"], "hello!");
+    assert_eq!(
+        event["resource"],
+        json!({ "type": "k8s_container", "labels": { "project_id": "my-project-id" } })
+    );
+    assert_eq!(
+        event["logging.googleapis.com/trace"],
+        format!("projects/my-project-id/traces/{TRACE_CONTEXT}")
+    );
+    assert!(!event.contains_key("logging.googleapis.com/spanId"));
+    assert!(!event.contains_key("logging.googleapis.com/trace_sampled"));
+}
+
+#[test]
+fn formats_generic_entries() {
+    let event = format_platform_entry(Platform::Generic);
+
+    assert_eq!(event["jsonPayload"]["message"], "hello!");
+    assert_eq!(
+        event["resource"],
+        json!({ "type": "global", "labels": { "project_id": "my-project-id" } })
+    );
+    assert_eq!(
+        event["logging.googleapis.com/trace"],
+        format!("projects/my-project-id/traces/{TRACE_CONTEXT}")
+    );
+    assert!(!event.contains_key("logging.googleapis.com/spanId"));
+}
+
+#[test]
+fn omits_resources_without_a_platform() {
+    let layer = tracing_stackdriver::layer().with_structured_payload(true);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!("hello!");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(!event.contains_key("resource"));
+}
+
+#[tracing::instrument]
+fn instrumented_function() {
+    tracing::info!("hello from an instrumented function!");
+}
+
+#[test]
+fn emits_a_single_source_location() {
+    let layer = tracing_stackdriver::layer()
+        .with_structured_payload(true)
+        .with_source_function_from_span(true);
+
+    let output = run_with_tracing_layer_raw(layer, instrumented_function);
+    let output = String::from_utf8(output).expect("Invalid UTF-8 output");
+
+    assert_eq!(
+        output
+            .matches("\"logging.googleapis.com/sourceLocation\"")
+            .count(),
+        1,
+        "{output}"
+    );
+
+    let event: Map<String, Value> =
+        serde_json::from_str(&output).expect("Error converting test buffer to JSON");
+
+    assert_eq!(
+        event["logging.googleapis.com/sourceLocation"]["function"],
+        "instrumented_function"
+    );
+    assert!(!event["jsonPayload"]
+        .as_object()
+        .expect("No jsonPayload found")
+        .contains_key("logging.googleapis.com/sourceLocation"));
+}
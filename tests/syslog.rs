@@ -0,0 +1,41 @@
+#![cfg(feature = "syslog")]
+use helpers::run_with_tracing_layer_raw;
+use tracing_stackdriver::OutputFormat;
+
+mod helpers;
+mod mocks;
+
+#[test]
+fn formats_entries_as_syslog() {
+    let layer = tracing_stackdriver::layer()
+        .with_source_location(false)
+        .with_format(OutputFormat::Syslog);
+
+    let output = run_with_tracing_layer_raw(layer, || {
+        tracing::warn!(
+            user = "some \"quoted\" user",
+            labels.env = "prod",
+            "disk almost full"
+        )
+    });
+
+    let output = String::from_utf8(output).expect("Invalid UTF-8 output");
+    let line = output.strip_suffix('\n').expect("Missing line ending");
+    let (header, rest) = line.split_once(" [").expect("Missing structured data");
+    let header: Vec<_> = header.split(' ').collect();
+
+    // facility user (1) * 8 + severity warning (4)
+    assert_eq!(header[0], "<12>1");
+    assert!(header[1].ends_with('Z'));
+    assert!(header[1]
+        .split_once('.')
+        .is_none_or(|(_, fraction)| fraction.len() <= 7));
+    assert_eq!(header[2], "-");
+    assert_eq!(header[3], "syslog");
+    assert_eq!(header[4], std::process::id().to_string());
+    assert_eq!(header[5], "-");
+    assert_eq!(
+        rest,
+        r#"fields@32473 labels="{\"env\":\"prod\"}" user="some \"quoted\" user"] disk almost full"#
+    );
+}
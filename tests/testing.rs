@@ -0,0 +1,26 @@
+#![cfg(feature = "testing")]
+use std::time::Duration;
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+#[test]
+fn reads_entries_from_background_tasks() {
+    let (layer, reader) = tracing_stackdriver::layer().with_writer_make_for_test();
+    let dispatch = tracing::Dispatch::new(Registry::default().with(layer));
+
+    let task = std::thread::spawn(move || {
+        tracing::dispatcher::with_default(&dispatch, || {
+            std::thread::sleep(Duration::from_millis(50));
+            tracing::info!(task = "background", "background entry");
+        })
+    });
+
+    let entry = reader
+        .recv_timeout(Duration::from_secs(5))
+        .expect("No entry heard");
+
+    task.join().expect("Background task panicked");
+
+    assert_eq!(entry["message"], "background entry");
+    assert_eq!(entry["task"], "background");
+    assert!(reader.try_recv().is_none());
+}
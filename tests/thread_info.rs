@@ -0,0 +1,39 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use serde_json::{Map, Value};
+use tracing_stackdriver::ThreadInfo;
+
+mod helpers;
+
+#[test]
+fn includes_thread_info() {
+    let layer = tracing_stackdriver::layer().with_thread_info(ThreadInfo {
+        name: true,
+        id: true,
+    });
+
+    let events = std::thread::Builder::new()
+        .name("worker".into())
+        .spawn(|| {
+            run_with_tracing_layer::<Map<String, Value>>(layer, || {
+                tracing::info!("from a named thread")
+            })
+        })
+        .expect("Error spawning thread")
+        .join()
+        .expect("Error joining thread")
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["thread"]["name"], "worker");
+    assert!(event["thread"]["id"].is_u64());
+}
+
+#[test]
+fn omits_thread_info_by_default() {
+    let events =
+        run_with_tracing::<Map<String, Value>>(|| tracing::info!("some stackdriver message"))
+            .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert!(!event.contains_key("thread"));
+}
@@ -0,0 +1,49 @@
+use helpers::{run_with_tracing, run_with_tracing_layer};
+use serde_json::{json, Map, Value};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+mod helpers;
+
+#[test]
+fn renames_fields_that_collide_with_the_timestamp() {
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing::info!(time = "x", timestamp = "y", "some stackdriver message")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    let time = event
+        .get("time")
+        .and_then(|time| time.as_str())
+        .expect("No time");
+
+    assert!(OffsetDateTime::parse(time, &Rfc3339).is_ok());
+    assert_eq!(event.get("timeField"), Some(&json!("x")));
+    assert_eq!(event.get("timestampField"), Some(&json!("y")));
+    assert!(!event.contains_key("timestamp"));
+}
+
+#[test]
+fn includes_receive_timestamp_when_configured() {
+    let emit = || tracing::info!("some stackdriver message");
+
+    let default =
+        run_with_tracing::<Map<String, Value>>(emit).expect("Error converting test buffer to JSON");
+
+    assert!(!default[0].contains_key("receiveTimestamp"));
+
+    let layer = tracing_stackdriver::layer().with_receive_timestamp(true);
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, emit)
+        .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+
+    for key in ["time", "receiveTimestamp"] {
+        let timestamp = event
+            .get(key)
+            .and_then(|timestamp| timestamp.as_str())
+            .expect("No timestamp");
+
+        assert!(OffsetDateTime::parse(timestamp, &Rfc3339).is_ok());
+    }
+}
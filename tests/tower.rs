@@ -0,0 +1,50 @@
+#![cfg(feature = "tower")]
+use helpers::run_with_tracing;
+use std::time::Duration;
+use tower_http::trace::{MakeSpan, OnResponse};
+use tracing_stackdriver::{StackdriverMakeSpan, StackdriverOnResponse};
+
+mod helpers;
+
+#[test]
+fn captures_http_requests_from_trace_layer() {
+    let request = http::Request::get("/users/42")
+        .header(http::header::USER_AGENT, "test-agent")
+        .body(())
+        .expect("Error building request");
+
+    let response = http::Response::builder()
+        .status(http::StatusCode::NOT_FOUND)
+        .header(http::header::CONTENT_LENGTH, "12")
+        .body(())
+        .expect("Error building response");
+
+    let events = run_with_tracing::<serde_json::Map<String, serde_json::Value>>(|| {
+        let span = StackdriverMakeSpan.make_span(&request);
+        span.in_scope(|| tracing::info!("handling request"));
+        StackdriverOnResponse.on_response(&response, Duration::from_millis(1500), &span);
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let handling = events.first().expect("No handling event heard");
+    assert_eq!(handling["httpRequest"]["requestMethod"], "GET");
+    assert_eq!(handling["httpRequest"]["requestUrl"], "/users/42");
+    assert_eq!(handling["httpRequest"]["userAgent"], "test-agent");
+    assert!(handling["span"]
+        .get("http_request.request_method")
+        .is_none());
+
+    let finished = events.get(1).expect("No response event heard");
+    assert_eq!(
+        finished["httpRequest"],
+        serde_json::json!({
+            "requestMethod": "GET",
+            "requestUrl": "/users/42",
+            "protocol": "HTTP/1.1",
+            "userAgent": "test-agent",
+            "status": 404,
+            "latency": "1.5s",
+            "responseSize": 12,
+        })
+    );
+}
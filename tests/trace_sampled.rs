@@ -0,0 +1,40 @@
+use helpers::run_with_tracing;
+use serde_json::{json, Map, Value};
+
+mod helpers;
+
+#[test]
+fn includes_trace_sampled_when_true() {
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing::info!(trace_sampled = true, "sampled by a boolean");
+        tracing::info!(trace_sampled = "1", "sampled by a header flag");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 2);
+
+    for event in events {
+        assert_eq!(
+            event.get("logging.googleapis.com/trace_sampled"),
+            Some(&json!(true))
+        );
+        assert!(!event.contains_key("traceSampled"));
+    }
+}
+
+#[test]
+fn omits_trace_sampled_when_false() {
+    let events = run_with_tracing::<Map<String, Value>>(|| {
+        tracing::info!(trace_sampled = false, "explicitly unsampled");
+        tracing::info!(trace_sampled = "0", "unsampled by a header flag");
+        tracing::info!("unsampled by default");
+    })
+    .expect("Error converting test buffer to JSON");
+
+    assert_eq!(events.len(), 3);
+
+    for event in events {
+        assert!(!event.contains_key("logging.googleapis.com/trace_sampled"));
+        assert!(!event.contains_key("traceSampled"));
+    }
+}
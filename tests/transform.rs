@@ -0,0 +1,51 @@
+use helpers::run_with_tracing_layer;
+use serde_json::{json, Map, Value};
+use tracing_stackdriver::{FieldTransform, Redact};
+
+mod helpers;
+mod mocks;
+
+/// Appends a marker to every string field, showing the order transforms are applied in
+struct Mark;
+
+impl FieldTransform for Mark {
+    fn transform(&self, _name: &str, value: &mut Value) {
+        if let Value::String(value) = value {
+            value.push('!');
+        }
+    }
+}
+
+#[test]
+fn applies_transforms_in_order() {
+    let layer = tracing_stackdriver::layer().with_transforms(vec![
+        Box::new(Redact::fields(&["password", "api_token"])),
+        Box::new(Mark),
+        Box::new(|name: &str, value: &mut Value| {
+            if name == "attempts" {
+                *value = json!(value.as_u64().unwrap_or_default() * 10);
+            }
+        }),
+    ]);
+
+    let events = run_with_tracing_layer::<Map<String, Value>>(layer, || {
+        tracing::info!(
+            password = "hunter2",
+            username = "someone",
+            attempts = 3,
+            labels.api_token = "secret",
+            "logging in"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.get("password"), Some(&json!("[REDACTED]!")));
+    assert_eq!(event.get("username"), Some(&json!("someone!")));
+    assert_eq!(event.get("attempts"), Some(&json!(30)));
+    assert_eq!(event.get("message"), Some(&json!("logging in!")));
+    assert_eq!(
+        event["logging.googleapis.com/labels"]["apiToken"],
+        json!("[REDACTED]!")
+    );
+}
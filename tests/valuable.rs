@@ -1,10 +1,17 @@
 #![allow(clippy::disallowed_names)]
 #![cfg(all(tracing_unstable, feature = "valuable"))]
-use helpers::run_with_tracing;
+use helpers::{run_with_tracing, run_with_tracing_layer};
 use mocks::{MockDefaultEvent, MockHttpEvent};
 use serde::Deserialize;
-use std::fmt::Debug;
-use tracing_stackdriver::LogSeverity;
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    time::{Duration, Instant, SystemTime},
+};
+use time::OffsetDateTime;
+use tracing_stackdriver::{
+    DurationFormat, IpFormat, LogDuration, LogInstant, LogSeverity, LogTimestamp,
+};
 use valuable::Valuable;
 
 mod helpers;
@@ -60,6 +67,28 @@ fn validates_structured_http_requests() {
     assert_eq!(event.http_request.remote_ip, remote_ip.to_string());
 }
 
+#[test]
+fn formats_structured_http_request_ips() {
+    let http_request = tracing_stackdriver::HttpRequest {
+        remote_ip: Some("2001:db8::1".parse().unwrap()),
+        server_ip: Some(std::net::IpAddr::from([10, 0, 0, 1])),
+        ..Default::default()
+    };
+
+    let layer = tracing_stackdriver::layer().with_ip_format(IpFormat::BracketedV6);
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        tracing::info!(
+            http_request = http_request.as_value(),
+            "http_request testing"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event["httpRequest"]["remoteIp"], "[2001:db8::1]");
+    assert_eq!(event["httpRequest"]["serverIp"], "10.0.0.1");
+}
+
 #[derive(Debug, Deserialize, Valuable, PartialEq)]
 struct StructuredLog {
     foo: String,
@@ -90,3 +119,171 @@ fn includes_valuable_structures() {
     let event = events.first().expect("No event heard");
     assert_eq!(event.structured_log, structured_log);
 }
+
+#[test]
+fn includes_valuable_structures_in_spans() {
+    let mut bar = std::collections::BTreeMap::new();
+    bar.insert("baz".into(), 123);
+    let structured_log = StructuredLog {
+        foo: "testing".to_string(),
+        bar,
+    };
+
+    let events = run_with_tracing::<BTreeMap<String, serde_json::Value>>(|| {
+        let span = tracing::info_span!("structured", data = structured_log.as_value());
+        let _span = span.enter();
+        tracing::info!("another message")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event["span"]["data"],
+        serde_json::json!({ "foo": "testing", "bar": { "baz": 123 } })
+    );
+}
+
+#[test]
+fn formats_valuable_durations() {
+    let duration = LogDuration(Duration::from_millis(1500));
+
+    let cases = [
+        (None, serde_json::json!("1.5s")),
+        (
+            Some(DurationFormat::ProtoDuration),
+            serde_json::json!("1.5s"),
+        ),
+        (Some(DurationFormat::Seconds), serde_json::json!(1.5)),
+        (Some(DurationFormat::Millis), serde_json::json!(1500.0)),
+    ];
+
+    for (duration_format, expected) in cases {
+        let layer = match duration_format {
+            Some(duration_format) => {
+                tracing_stackdriver::layer().with_duration_format(duration_format)
+            }
+            None => tracing_stackdriver::layer(),
+        };
+
+        let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+            tracing::info!(elapsed = duration.as_value(), "duration testing")
+        })
+        .expect("Error converting test buffer to JSON");
+
+        let event = events.first().expect("No event heard");
+        assert_eq!(event.get("elapsed"), Some(&expected));
+    }
+}
+
+#[derive(Valuable)]
+struct Item {
+    item_name: &'static str,
+    unit_price: u16,
+}
+
+#[test]
+fn applies_deep_casing_to_valuable_lists() {
+    let items = vec![
+        Item {
+            item_name: "widget",
+            unit_price: 10,
+        },
+        Item {
+            item_name: "gadget",
+            unit_price: 20,
+        },
+    ];
+
+    let layer = tracing_stackdriver::layer().with_deep_casing(true);
+
+    let events = run_with_tracing_layer::<BTreeMap<String, serde_json::Value>>(layer, || {
+        tracing::info!(cart_items = items.as_value(), "checkout")
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event.get("cartItems"),
+        Some(&serde_json::json!([
+            { "itemName": "widget", "unitPrice": 10 },
+            { "itemName": "gadget", "unitPrice": 20 },
+        ]))
+    );
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MockTimestampEvent {
+    #[serde(deserialize_with = "time::serde::rfc3339::deserialize")]
+    expires_at: OffsetDateTime,
+}
+
+#[test]
+fn formats_valuable_timestamps() {
+    let expires_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+    let events = run_with_tracing::<MockTimestampEvent>(|| {
+        tracing::info!(
+            expires_at = LogTimestamp(expires_at).as_value(),
+            "timestamp testing"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(SystemTime::from(event.expires_at), expires_at);
+}
+
+#[test]
+fn emits_valuable_instants_as_null() {
+    let events = run_with_tracing::<BTreeMap<String, serde_json::Value>>(|| {
+        tracing::info!(
+            deadline = LogInstant(Instant::now()).as_value(),
+            "instant testing"
+        )
+    })
+    .expect("Error converting test buffer to JSON");
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(event.get("deadline"), Some(&serde_json::Value::Null));
+}
+
+#[derive(Valuable)]
+struct Node {
+    depth: u32,
+    child: Option<Box<Node>>,
+}
+
+#[test]
+fn truncates_deeply_nested_values() {
+    let node = (0..100_000).fold(
+        Node {
+            depth: 0,
+            child: None,
+        },
+        |child, depth| Node {
+            depth: depth + 1,
+            child: Some(Box::new(child)),
+        },
+    );
+
+    let layer = tracing_stackdriver::layer().with_max_value_depth(2);
+
+    let events =
+        run_with_tracing_layer::<serde_json::Map<String, serde_json::Value>>(layer, || {
+            tracing::info!(node = node.as_value(), "nested")
+        })
+        .expect("Error converting test buffer to JSON");
+
+    // dropping the node recursively would overflow the stack as well, so it's leaked instead
+    std::mem::forget(node);
+
+    let event = events.first().expect("No event heard");
+    assert_eq!(
+        event["node"],
+        serde_json::json!({
+            "depth": 100_000,
+            "child": { "depth": 99_999, "child": { "__truncated": true } },
+        })
+    );
+}